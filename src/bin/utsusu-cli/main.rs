@@ -1,14 +1,15 @@
 use std::env;
 use std::process::exit;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
 use utsusu::utils::{get_user_input, get_user_variable_choices};
-use utsusu::template_rendering::{load_template_files_from_filenames, get_all_template_filenames_from_directory};
-use utsusu::template_rendering::single_file_render::render_single_file;
-use utsusu::template_config::{parse_config_from_file, TemplateOutputType};
+use utsusu::template::{find_template_collection, Template, TemplateLoadError, TemplateRenderError};
+use utsusu::template_rendering::directory_render::DirectoryRenderError;
+use utsusu::template_rendering::diagnostics::get_source_snippet;
+use utsusu::template_config::{ConfigParseFromFileError, TemplateOutputType};
 
 // CLI parsing:
 // - Should be as simple as specifying the template name as a positional argument
@@ -16,13 +17,12 @@ use utsusu::template_config::{parse_config_from_file, TemplateOutputType};
 // - Should allow specifying the template directory via flag
 
 const DEFAULT_CONFIG_FILE: &str = "config.yml";
-const DEFAULT_TEMPLATE_CONFIG_FILE: &str = "config.yml";
 const DEFAULT_TEMPLATE_DIR: &str = "templates";
-const TEMPLATE_FILES_DIR: &str = "files";
 
 const CONFIG_FILE_PARAM_NAME: &str = "config_file";
 const TEMPLATES_DIR_PARAM_NAME: &str = "templates_directory";
 const TEMPLATE_NAME_PARAM_NAME: &str = "template_name";
+const ADVANCED_PARAM_NAME: &str = "advanced";
 
 const CONFIG_FILE_ENV_NAME: &str = "UTSUSU_CONFIG_FILE";
 const TEMPLATES_DIR_ENV_NAME: &str = "UTSUSU_TEMPLATES_DIR";
@@ -70,6 +70,12 @@ pub fn main() {
 				.value_name("TEMPLATES_DIR")
 				.help(format!("Path to the directory containing templates to render{}", help_string_default_template_dir_path))
 		)
+        .arg(
+            Arg::new(ADVANCED_PARAM_NAME)
+                .long("advanced")
+                .action(ArgAction::SetTrue)
+                .help("Also prompt for the template's advanced variables, instead of using their defaults")
+        )
         .arg(
             Arg::new(TEMPLATE_NAME_PARAM_NAME)
                 .required(true)
@@ -88,7 +94,7 @@ pub fn main() {
             } else {
                 // Can't find the file, error and tell the user to explicitly specify the config
                 // file path
-                eprintln!("Cannot find default configuration file path, specify explicitly via the {} environment variable or via the flag {}", CONFIG_FILE_ENV_NAME, "--config");
+                eprintln!("Cannot find default configuration file path, specify explicitly via the {} environment variable or via the flag --config", CONFIG_FILE_ENV_NAME);
                 exit(1);
             }
         },
@@ -107,7 +113,7 @@ pub fn main() {
             } else {
                 // Can't find the templates directory path, error and tell the user to explicitly specify the templates
                 // directory path
-                eprintln!("Cannot find default templates directory path, specify explicitly via the {} environment variable or via the flag {}", TEMPLATES_DIR_ENV_NAME, "--templates-dir");
+                eprintln!("Cannot find default templates directory path, specify explicitly via the {} environment variable or via the flag --templates-dir", TEMPLATES_DIR_ENV_NAME);
                 exit(1);
             }
         },
@@ -127,218 +133,157 @@ pub fn main() {
     let requested_template_path = templates_dir_path.join(requested_template_name);
     println!("Template Path: {:?}", requested_template_path);
 
-    // Validate that the template path exists
-    if !requested_template_path.is_dir() {
+    // Validate that the template path exists, or that there's a collection of templates it could be in
+    if !requested_template_path.is_dir() && find_template_collection(&requested_template_path).is_none() {
         eprintln!("Template does not exist at path '{}'", requested_template_path.display());
         exit(1);
     }
 
-    // Pull config file from template directory
-    let template_config_file_path = requested_template_path.join(DEFAULT_TEMPLATE_CONFIG_FILE);
-    println!("Using config file at: {}", template_config_file_path.display());
-
-    let template_config_res = parse_config_from_file(&template_config_file_path);
-
-    if template_config_res.is_err() {
-        println!("Error parsing configuration: {:?}", template_config_res.unwrap_err());
-        exit(-1);
-    }
-
-    let template_config = template_config_res.unwrap();
-
-    // Aggregate the template files that should be rendered
-    let mut template_files_to_render: Vec<PathBuf> = Vec::new();
-
-    let template_files_path = requested_template_path.join(TEMPLATE_FILES_DIR);
-    let res = get_all_template_filenames_from_directory(&template_files_path);
-    if let Ok(files) = res {
-        for f in files {
-            if let Ok(files_dir_relative_filename) = f.strip_prefix(&template_files_path) {
-                if template_config.should_include_file(&files_dir_relative_filename) {
-                    template_files_to_render.push(f);
-                }
+    // Load the template: the config file plus the template files that should be rendered
+    let template = match Template::load(&requested_template_path) {
+        Err(TemplateLoadError::TemplateNotFound(path)) => {
+            eprintln!("Template does not exist at path '{}'", path.display());
+            exit(1);
+        },
+        Err(TemplateLoadError::ConfigError(ConfigParseFromFileError::ParseError(config_path, parse_error))) => {
+            println!("Error parsing configuration '{}': {}", config_path.display(), parse_error);
+            let snippet = parse_error.get_location()
+                .zip(std::fs::read_to_string(&config_path).ok())
+                .and_then(|((line, column), config_str)| get_source_snippet(&config_str, line, column, 1));
+            if let Some(snippet) = snippet {
+                println!("{}", snippet);
             }
-        }
-    } else {
-        println!("Error reading template files: {}", res.unwrap_err());
-        exit(-2);
-    }
-
-    if template_files_to_render.len() == 0 {
-        println!("No matching template files to render. Adjust your included files glob to match at least one file.");
-        exit(-5);
-    }
+            exit(-1);
+        },
+        Err(TemplateLoadError::ConfigError(config_error)) => {
+            println!("Error loading configuration: {}", config_error);
+            exit(-1);
+        },
+        Err(TemplateLoadError::FileReadError(read_error)) => {
+            println!("Error reading template files: {}", read_error);
+            exit(-2);
+        },
+        Err(TemplateLoadError::NoMatchingFiles) => {
+            println!("No matching template files to render. Adjust your included files glob to match at least one file.");
+            exit(-5);
+        },
+        Err(TemplateLoadError::TooManyMatchingFiles) => {
+            println!("Cannot render more than 1 file for a 'File' type template. Adjust your included files glob to match a single file.");
+            exit(-3);
+        },
+        Err(TemplateLoadError::TemplateFileLoadError(tera_error)) => {
+            println!("Error loading template files: {}", tera_error);
+            exit(-4);
+        },
+        Err(TemplateLoadError::InheritanceCycle(template_paths)) |
+        Err(TemplateLoadError::SubtemplateCycle(template_paths)) => {
+            let chain: Vec<String> = template_paths.iter().map(|p| p.display().to_string()).collect();
+            println!("Templates extend or include each other in a loop: {}", chain.join(" -> "));
+            exit(-16);
+        },
+        Err(TemplateLoadError::SubtemplateError(subtemplate_name, load_error)) => {
+            println!("Error loading subtemplate '{}': {}", subtemplate_name, load_error);
+            exit(-17);
+        },
+        Ok(template) => template,
+    };
+    println!("Using config file at: {}", template.get_config_path().display());
 
     // Get user values for variables
     // -- Output filename/directory is always needed
-    let (user_output_filename, user_output_directory) = {
-        match template_config.get_output_type() {
-            TemplateOutputType::File => {
-                let output_opt = get_user_input(&format!("Output File [{}]: ", template_config.get_output_filename().unwrap_or("rendered")));
-                (output_opt, None)
-            },
-            TemplateOutputType::Directory => {
-                let output_opt = get_user_input(&format!("Output Directory [{}]: ", template_config.get_output_directory().unwrap_or("rendered")));
-                (None, output_opt)
-            },
+    let user_output_path = {
+        match template.get_output_type() {
+            TemplateOutputType::File => get_user_input(&format!("Output File [{}]: ", template.get_default_output_path())),
+            TemplateOutputType::Directory => get_user_input(&format!("Output Directory [{}]: ", template.get_default_output_path())),
+            TemplateOutputType::Inject => get_user_input(&format!("Target File [{}]: ", template.get_default_output_path())),
         }
     };
 
     // -- Template variables
-    let user_variables_context = get_user_variable_choices(&template_config);
+    let user_variables_context = get_user_variable_choices(template.get_config(), matches.get_flag(ADVANCED_PARAM_NAME));
 
     // Do the output rendering
-    match template_config.get_output_type() {
-        TemplateOutputType::File => {
-            if template_files_to_render.len() > 1 {
-                println!("Cannot render more than 1 file for a 'File' type template. Adjust your included files glob to match a single file.");
-                exit(-3);
-            }
-
-            match load_template_files_from_filenames(&template_files_to_render) {
-                Err(tera_error) => {
-                    println!("Error loading template files: {}", tera_error);
-                    exit(-4);
-                },
-                Ok(tera) => {
-                    let template_source_file_path = &template_files_to_render[0]; // Safety: Due to previous checks, this will always have exactly 1 element
-
-                    // Render out the final file path, in case the user named something using a
-                    // variable
-                    let output_file_path_raw = user_output_filename.or_else(|| template_config.get_output_filename().and_then(|s| Some(s.to_string()))).unwrap_or(String::new());
-                    let mut full_context = template_config.get_render_context();
-                    full_context.extend(user_variables_context.clone());
-                    match tera::Tera::one_off(&output_file_path_raw, &full_context, true) {
-                        Err(tera_error) => {
-                            println!("Error rendering path '{}': {}", output_file_path_raw, tera_error);
-                            exit(-9);
-                        },
-                        Ok(output_file_path) => {
-                            match render_single_file(&tera, &template_config, &template_source_file_path.display().to_string(), Some(&user_variables_context)) {
-                                Err(tera_error) => {
-                                    println!("Error rendering template file: {}", tera_error);
-                                    println!("Source file: {}", template_source_file_path.display());
-                                    //println!("All template files: {:?}", template_files_to_render);
-                                    //println!("Registered templates: {:?}", tera.get_template_names().collect::<Vec<_>>());
-                                    exit(-6);
-                                },
-                                Ok(rendered_string) => {
-                                    // Write the rendered string to the output file
-                                    // -- Ensure the parent directories exist
-                                    if let Some(final_directory) = PathBuf::from(&output_file_path).parent() {
-                                        if !final_directory.exists() {
-                                            if let Err(mkdir_error) = std::fs::create_dir_all(final_directory) {
-                                                println!("Error creating directories for file '{}': {}", output_file_path, mkdir_error);
-                                                exit(-10);
-                                            }
-                                        }
-                                    }
-
-                                    // -- Write the rendered file
-                                    let write_res = std::fs::write(&output_file_path, rendered_string);
-                                    if write_res.is_err() {
-                                        println!("Error writing rendered file: {}", write_res.unwrap_err());
-                                        exit(-7);
-                                    } else {
-                                        println!("Template written to '{}'", output_file_path);
-                                        exit(0);
-                                    }
-                                },
-                            };
-                        },
-                    };
-                },
+    match template.render(Some(&user_variables_context), user_output_path.as_deref().map(Path::new)) {
+        Err(TemplateRenderError::ContextError(tera_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::ContextError(tera_error))) => {
+            println!("Error building the render context: {}", tera_error);
+            exit(-13);
+        },
+        Err(TemplateRenderError::InvalidVariableValue(variable_name, value_error)) => {
+            println!("Invalid value for variable '{}': {}", variable_name, value_error);
+            exit(-12);
+        },
+        Err(TemplateRenderError::OutputPathRenderError(path, tera_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::PathRenderError(path, tera_error))) => {
+            println!("Error rendering path '{}': {}", path, tera_error);
+            exit(-9);
+        },
+        Err(TemplateRenderError::FileRenderError(template_name, tera_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::TemplateRenderError(template_name, tera_error))) => {
+            println!("Error rendering template file '{}':", template_name);
+            println!("{}", template.diagnose_render_error(&template_name, &tera_error));
+            exit(-6);
+        },
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::ConditionError(source_path, tera_error))) => {
+            println!("Error evaluating the condition for file '{}': {}", source_path.display(), tera_error);
+            exit(-13);
+        },
+        Err(TemplateRenderError::ReadError(source_path, read_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::ReadError(source_path, read_error))) => {
+            println!("Error reading template file '{}': {}", source_path.display(), read_error);
+            exit(-2);
+        },
+        Err(TemplateRenderError::InjectError(target_path, inject_error)) => {
+            println!("Error injecting into '{}': {}", target_path.display(), inject_error);
+            exit(-19);
+        },
+        Err(TemplateRenderError::MergeError(target_path, merge_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::MergeError(target_path, merge_error))) => {
+            println!("Error merging into '{}': {}", target_path.display(), merge_error);
+            exit(-20);
+        },
+        Err(TemplateRenderError::SubtemplateRenderError(subtemplate_name, subtemplate_error)) => {
+            println!("Error rendering subtemplate '{}':", subtemplate_name);
+            let diagnostic = template.get_subtemplate(&subtemplate_name)
+                .and_then(|subtemplate| subtemplate.diagnose_template_render_error(&subtemplate_error));
+            match diagnostic {
+                Some(diagnostic) => println!("{}", diagnostic),
+                None => println!("{}", subtemplate_error),
             };
+            exit(-18);
         },
-        TemplateOutputType::Directory => {
-            match load_template_files_from_filenames(&template_files_to_render) {
-                Err(tera_error) => {
-                    println!("Error loading template files: {}", tera_error);
-                    exit(-4);
-                },
-                Ok(tera) => {
-                    // Render out the output directory path, in case the user named something using a
-                    // variable
-                    let output_directory_path_raw = PathBuf::from(user_output_directory.or_else(|| template_config.get_output_directory().and_then(|s| Some(s.to_string()))).unwrap_or(String::new()));
-                    let mut full_context = template_config.get_render_context();
-                    full_context.extend(user_variables_context.clone());
-                    match tera::Tera::one_off(&output_directory_path_raw.display().to_string(), &full_context, true) {
-                        Err(tera_error) => {
-                            println!("Error rendering path '{}': {}", output_directory_path_raw.display(), tera_error);
-                            exit(-9);
-                        },
-                        Ok(output_directory_path_string) => {
-                            let output_directory_path = PathBuf::from(output_directory_path_string);
-
-                            // Create the output directory
-                            // -- Verify it doesn't already exist
-                            if output_directory_path.exists() {
-                                println!("Error: Directory already exists");
-                                exit(-11);
-                            }
-
-                            // -- Create the directory (and all parent directories)
-                            if let Err(fs_error) = std::fs::create_dir_all(&output_directory_path) {
-                                println!("Error creating output directory: {}", fs_error);
-                                exit(-8);
-                            }
-
-                            // Render all the files to the output directory
-                            let total_template_files = template_files_to_render.len();
-                            let mut total_template_files_written = 0;
-                            for template_source_file_path in &template_files_to_render {
-                                if let Ok(files_dir_relative_filename) = template_source_file_path.strip_prefix(&template_files_path) {
-                                    let output_file_path_raw = output_directory_path.join(files_dir_relative_filename);
-                                    match tera::Tera::one_off(&output_file_path_raw.display().to_string(), &full_context, true) {
-                                        Err(tera_error) => {
-                                            println!("Error rendering path '{}': {}", output_file_path_raw.display(), tera_error);
-                                            exit(-9);
-                                        },
-                                        Ok(output_file_path_string) => {
-                                            let output_file_path = PathBuf::from(output_file_path_string);
-                                            match render_single_file(&tera, &template_config, &template_source_file_path.display().to_string(), Some(&user_variables_context)) {
-                                                Err(tera_error) => {
-                                                    println!("Error rendering template file: {}", tera_error);
-                                                    println!("Source file: {}", template_source_file_path.display());
-                                                    println!("All template files: {:?}", template_files_to_render);
-                                                    println!("Registered templates: {:?}", tera.get_template_names().collect::<Vec<_>>());
-                                                    exit(-6);
-                                                },
-                                                Ok(rendered_string) => {
-                                                    // Write the rendered string to the output file
-                                                    // -- Ensure the parent directories exist
-                                                    if let Some(final_directory) = PathBuf::from(&output_file_path).parent() {
-                                                        if !final_directory.exists() {
-                                                            if let Err(mkdir_error) = std::fs::create_dir_all(final_directory) {
-                                                                println!("Error creating directories for file '{}': {}", output_file_path.display(), mkdir_error);
-                                                                exit(-10);
-                                                            }
-                                                        }
-                                                    }
-
-                                                    // -- Write the rendered file
-                                                    let write_res = std::fs::write(&output_file_path, rendered_string);
-                                                    if write_res.is_err() {
-                                                        println!("Error writing rendered file: {}", write_res.unwrap_err());
-                                                        println!("Output file path: {}", output_file_path.display());
-                                                        exit(-7);
-                                                    } else {
-                                                        total_template_files_written += 1;
-                                                    }
-                                                },
-                                            };
-                                        }
-                                    }
-                                }
-                            }
-                            println!("{}/{} files written to '{}'", total_template_files_written, total_template_files, output_directory_path.display());
-                            exit(0);
-                        }
-                    }
-                },
+        Err(TemplateRenderError::PreRenderHookError(hook_error)) => {
+            println!("Error running pre-render hook: {}", hook_error);
+            exit(-14);
+        },
+        Err(TemplateRenderError::PostRenderHookError(hook_error)) => {
+            println!("Error running post-render hook: {}", hook_error);
+            exit(-15);
+        },
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::OutputDirectoryExists(_))) => {
+            println!("Error: Directory already exists");
+            exit(-11);
+        },
+        Err(TemplateRenderError::CreateDirectoryError(directory, mkdir_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::CreateDirectoryError(directory, mkdir_error))) => {
+            println!("Error creating directory '{}': {}", directory.display(), mkdir_error);
+            exit(-10);
+        },
+        Err(TemplateRenderError::WriteError(output_file_path, write_error)) |
+        Err(TemplateRenderError::DirectoryRenderError(DirectoryRenderError::WriteError(output_file_path, write_error))) => {
+            println!("Error writing rendered file: {}", write_error);
+            println!("Output file path: {}", output_file_path.display());
+            exit(-7);
+        },
+        Ok(rendered_template) => {
+            match template.get_output_type() {
+                TemplateOutputType::File if rendered_template.written_files.contains(&rendered_template.output_path) => println!("Template written to '{}'", rendered_template.output_path.display()),
+                TemplateOutputType::File => println!("'{}' is already up to date", rendered_template.output_path.display()),
+                TemplateOutputType::Directory => println!("{} files written to '{}'", rendered_template.written_files.len(), rendered_template.output_path.display()),
+                TemplateOutputType::Inject if rendered_template.written_files.contains(&rendered_template.output_path) => println!("Snippet injected into '{}'", rendered_template.output_path.display()),
+                TemplateOutputType::Inject => println!("'{}' already contains the snippet", rendered_template.output_path.display()),
             };
+            exit(0);
         },
-        //_ => println!("Unsupported output type: {:?}", template_config.get_output_type()),
     };
-
 }
@@ -1,4 +1,10 @@
 
+pub mod template;
 pub mod template_config;
 pub mod template_rendering;
+pub mod template_variable;
 pub mod utils;
+mod value_conversion;
+
+#[cfg(test)]
+mod test_utils;
@@ -0,0 +1,927 @@
+//! This module provides [Template], which bundles a template's configuration together with the
+//! files it renders, so that callers can load and render a template in a couple of calls.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera, Value};
+use crate::template_config::{parse_config_from_file, parse_inline_configs_from_file, ConfigParseErrorKind, ConfigParseFromFileError, InlineTemplateConfig, TemplateConfig, TemplateOutputType};
+use crate::template_rendering::{load_named_template_files, get_included_template_filenames_from_directory, is_binary_file, new_tera, render_one_off, render_path};
+use crate::template_rendering::single_file_render::render_single_file;
+use crate::template_rendering::directory_render::{create_output_directory, render_directory, write_rendered_files, DirectoryRenderError};
+use crate::template_rendering::hooks::{run_hooks, HookError};
+use crate::template_rendering::inject_render::{inject_snippet, InjectError};
+use crate::template_rendering::merge_render::{merge_documents, MergeError};
+use crate::template_rendering::diagnostics::{diagnose_render_error, RenderDiagnostic};
+use crate::template_variable::{is_template_string, VariableValueError};
+
+/// The name of the configuration file inside a template directory.
+pub const TEMPLATE_CONFIG_FILE: &str = "config.yml";
+
+/// The names the configuration file inside a template directory can have, in the order they're
+/// looked for. The format is taken from the extension.
+pub const TEMPLATE_CONFIG_FILES: [&str; 8] = [
+    TEMPLATE_CONFIG_FILE, "config.yaml", "config.toml", "config.json",
+    "utsusu.yml", "utsusu.yaml", "utsusu.toml", "utsusu.json",
+];
+
+/// The name of the file in a templates directory that holds a collection of templates given inline,
+/// as separate YAML documents.
+pub const TEMPLATE_COLLECTION_FILE: &str = "templates.yml";
+
+/// The name of the directory inside a template directory that holds the files to render.
+pub const TEMPLATE_FILES_DIR: &str = "files";
+
+/// The name used for the output when the template configuration doesn't provide one.
+const DEFAULT_OUTPUT_NAME: &str = "rendered";
+
+/// A template loaded from disk: its configuration, plus the files it will render.
+#[derive(Debug)]
+pub struct Template {
+    /// The name of the template, taken from the name of its directory.
+    name: String,
+
+    /// The path to the template directory.
+    path: PathBuf,
+
+    /// The path to the template's configuration file, or to the collection of templates it was
+    /// given inline in.
+    config_path: PathBuf,
+
+    /// The parsed configuration for the template, merged with that of any templates it extends.
+    config: TemplateConfig,
+
+    /// The files that matched the template's include globs, relative to the files directory.
+    files: Vec<PathBuf>,
+
+    /// The path each of `files` is read from, in the same order. Files can come from the files
+    /// directory of a template this one extends.
+    sources: Vec<PathBuf>,
+
+    /// The subset of `files` that are copied as-is instead of being rendered: those matching the
+    /// template's `copy_without_render` globs, and any binary files.
+    verbatim_files: Vec<PathBuf>,
+
+    /// The Tera instance with all the matched files loaded into it, named by their path relative to
+    /// the files directory.
+    tera: Tera,
+
+    /// The templates for each of the configuration's subtemplates, in the same order.
+    subtemplates: Vec<Template>,
+
+    /// The contents of the template's one file, if it was given inline in a collection of templates
+    /// (see [TEMPLATE_COLLECTION_FILE]) rather than in a files directory.
+    inline_body: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum TemplateLoadError {
+    TemplateNotFound(PathBuf),
+    ConfigError(ConfigParseFromFileError),
+    FileReadError(std::io::Error),
+    NoMatchingFiles,
+    TooManyMatchingFiles,
+    TemplateFileLoadError(tera::Error),
+    /// Templates extend each other in a loop: (the paths of the templates involved)
+    InheritanceCycle(Vec<PathBuf>),
+    /// Loading a subtemplate failed: (subtemplate name, error)
+    SubtemplateError(String, Box<TemplateLoadError>),
+    /// Templates use each other as subtemplates in a loop: (the paths of the templates involved)
+    SubtemplateCycle(Vec<PathBuf>),
+}
+
+#[derive(Debug)]
+pub enum TemplateRenderError {
+    /// Building the render context failed, e.g. because a computed default couldn't be rendered
+    ContextError(tera::Error),
+    /// A value given for a variable isn't valid for it: (variable name, error)
+    InvalidVariableValue(String, VariableValueError),
+    /// Rendering the output path failed: (raw path, error)
+    OutputPathRenderError(String, tera::Error),
+    /// Rendering the file of a File template failed: (template name, error)
+    FileRenderError(String, tera::Error),
+    /// A file could not be read, e.g. the file of a File template that is copied verbatim, or the
+    /// target file of an Inject template: (file, error)
+    ReadError(PathBuf, std::io::Error),
+    /// The snippet of an Inject template could not be put into its target file: (file, error)
+    InjectError(PathBuf, InjectError),
+    /// The rendered file could not be merged into the existing file: (file, error)
+    MergeError(PathBuf, MergeError),
+    /// A directory could not be created: (directory, error)
+    CreateDirectoryError(PathBuf, std::io::Error),
+    /// The rendered file could not be written: (file, error)
+    WriteError(PathBuf, std::io::Error),
+    /// Rendering or writing a Directory template failed
+    DirectoryRenderError(DirectoryRenderError),
+    /// A pre-render hook failed; anything created for the output has been removed again
+    PreRenderHookError(HookError),
+    /// A post-render hook failed; the rendered files have already been written
+    PostRenderHookError(HookError),
+    /// Rendering a subtemplate failed: (subtemplate name, error)
+    SubtemplateRenderError(String, Box<TemplateRenderError>),
+}
+
+impl fmt::Display for TemplateLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateLoadError::TemplateNotFound(path) => write!(f, "template does not exist at path '{}'", path.display()),
+            TemplateLoadError::ConfigError(config_error) => write!(f, "could not load the configuration: {}", config_error),
+            TemplateLoadError::FileReadError(read_error) => write!(f, "could not read the template files: {}", read_error),
+            TemplateLoadError::NoMatchingFiles => write!(f, "no template files match the included globs"),
+            TemplateLoadError::TooManyMatchingFiles => write!(f, "file templates can only render a single file, but the included glob matches more"),
+            TemplateLoadError::TemplateFileLoadError(tera_error) => write!(f, "could not load the template files: {}", tera_error),
+            TemplateLoadError::SubtemplateError(subtemplate_name, load_error) => write!(f, "could not load subtemplate '{}': {}", subtemplate_name, load_error),
+            TemplateLoadError::InheritanceCycle(template_paths) | TemplateLoadError::SubtemplateCycle(template_paths) => {
+                let chain: Vec<String> = template_paths.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "templates extend or include each other in a loop: {}", chain.join(" -> "))
+            },
+        }
+    }
+}
+
+impl fmt::Display for TemplateRenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateRenderError::ContextError(tera_error) => write!(f, "could not build the render context: {}", tera_error),
+            TemplateRenderError::InvalidVariableValue(variable_name, value_error) => write!(f, "invalid value for variable '{}': {}", variable_name, value_error),
+            TemplateRenderError::OutputPathRenderError(path, tera_error) => write!(f, "could not render path '{}': {}", path, tera_error),
+            TemplateRenderError::FileRenderError(template_name, tera_error) => write!(f, "could not render template file '{}': {}", template_name, tera_error),
+            TemplateRenderError::ReadError(file, read_error) => write!(f, "could not read '{}': {}", file.display(), read_error),
+            TemplateRenderError::InjectError(file, inject_error) => write!(f, "could not inject into '{}': {}", file.display(), inject_error),
+            TemplateRenderError::MergeError(file, merge_error) => write!(f, "could not merge into '{}': {}", file.display(), merge_error),
+            TemplateRenderError::CreateDirectoryError(directory, mkdir_error) => write!(f, "could not create directory '{}': {}", directory.display(), mkdir_error),
+            TemplateRenderError::WriteError(file, write_error) => write!(f, "could not write '{}': {}", file.display(), write_error),
+            TemplateRenderError::DirectoryRenderError(directory_error) => write!(f, "{}", directory_error),
+            TemplateRenderError::PreRenderHookError(hook_error) => write!(f, "pre-render hook failed: {}", hook_error),
+            TemplateRenderError::PostRenderHookError(hook_error) => write!(f, "post-render hook failed: {}", hook_error),
+            TemplateRenderError::SubtemplateRenderError(subtemplate_name, render_error) => write!(f, "could not render subtemplate '{}': {}", subtemplate_name, render_error),
+        }
+    }
+}
+
+/// The result of successfully rendering a template to disk.
+#[derive(Debug, Clone)]
+pub struct RenderedTemplate {
+    /// The rendered output path: the output file for File templates, or the output directory for
+    /// Directory templates.
+    pub output_path: PathBuf,
+
+    /// Every file that was written.
+    pub written_files: Vec<PathBuf>,
+}
+
+impl Template {
+    /// Loads the template in the directory at `path`.
+    ///
+    /// This parses the template's configuration file (`config.yml`, or one of the other names in
+    /// [TEMPLATE_CONFIG_FILES]), finds every file in its `files` directory that
+    /// the configured include and exclude globs select, and loads them for rendering.
+    ///
+    /// If the template extends another (which is looked up next to this template's directory), the
+    /// parent's configuration is merged into this one (see [TemplateConfig::inherit_from]), and the
+    /// parent's files are included too, unless this template has a file at the same relative path.
+    ///
+    /// If there's no directory at `path`, the template is looked for by name in the collection of
+    /// templates given inline next to it (see [TEMPLATE_COLLECTION_FILE]).
+    ///
+    /// Any subtemplates (also looked up next to this template's directory) are loaded as well.
+    pub fn load<P: AsRef<Path>>(path: &P) -> Result<Self, TemplateLoadError> {
+        Self::load_with_parents(path.as_ref(), &mut Vec::new())
+    }
+
+    /// Loads every template in a collection of templates given inline (see [TEMPLATE_COLLECTION_FILE]),
+    /// in the order they're given. Each one is treated as if it were in its own directory next to
+    /// the collection file, so subtemplates are looked up next to the collection file.
+    pub fn load_collection<P: AsRef<Path>>(collection_path: &P) -> Result<Vec<Self>, TemplateLoadError> {
+        let collection_path = collection_path.as_ref();
+        let inline_configs = parse_inline_configs_from_file(&collection_path).map_err(TemplateLoadError::ConfigError)?;
+        let templates_dir = collection_path.parent().unwrap_or(Path::new(""));
+
+        let mut templates = Vec::with_capacity(inline_configs.len());
+        for inline_config in inline_configs {
+            let path = templates_dir.join(inline_config.get_name());
+            templates.push(Self::from_inline_config(&path, collection_path, inline_config, &mut Vec::new())?);
+        }
+        Ok(templates)
+    }
+
+    /// Loads the template at `path`, where `loading` holds the templates that are being loaded and
+    /// use this one as a subtemplate, to detect cycles.
+    ///
+    /// If there's no directory at `path`, the template is looked for by name in the collection of
+    /// templates next to it, if there is one.
+    fn load_with_parents(path: &Path, loading: &mut Vec<PathBuf>) -> Result<Self, TemplateLoadError> {
+        let path = path.to_path_buf();
+        if !path.is_dir() {
+            let collection_path = match find_template_collection(&path) {
+                Some(collection_path) => collection_path,
+                None => return Err(TemplateLoadError::TemplateNotFound(path)),
+            };
+
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let inline_config = parse_inline_configs_from_file(&collection_path).map_err(TemplateLoadError::ConfigError)?
+                .into_iter()
+                .find(|inline_config| inline_config.get_name() == name);
+            return match inline_config {
+                Some(inline_config) => Self::from_inline_config(&path, &collection_path, inline_config, loading),
+                None => Err(TemplateLoadError::TemplateNotFound(path)),
+            };
+        }
+
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+        let (config, files_roots) = load_config_with_parents(&path, &mut Vec::new())?;
+        if let Some(cycle) = config.find_variable_cycle() {
+            return Err(TemplateLoadError::ConfigError(ConfigParseFromFileError::ParseError(get_config_file_path(&path), Box::new(ConfigParseErrorKind::VariableDefaultCycle(cycle).into()))));
+        }
+        if let Some(cycle) = config.find_variable_group_cycle() {
+            return Err(TemplateLoadError::ConfigError(ConfigParseFromFileError::ParseError(get_config_file_path(&path), Box::new(ConfigParseErrorKind::VariableGroupCycle(cycle).into()))));
+        }
+
+        // Aggregate the template files that should be rendered, with files from later roots
+        // replacing those at the same path from earlier ones
+        let mut file_sources: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+        for files_root in files_roots.iter().filter(|root| root.is_dir()) {
+            match get_included_template_filenames_from_directory(files_root, &config) {
+                Err(read_error) => return Err(TemplateLoadError::FileReadError(read_error)),
+                Ok(included_files) => {
+                    for f in included_files {
+                        if let Ok(files_dir_relative_filename) = f.strip_prefix(files_root) {
+                            file_sources.insert(files_dir_relative_filename.to_path_buf(), f.clone());
+                        }
+                    }
+                },
+            };
+        }
+        let (files, sources): (Vec<PathBuf>, Vec<PathBuf>) = file_sources.into_iter().unzip();
+
+        if files.is_empty() {
+            return Err(TemplateLoadError::NoMatchingFiles);
+        }
+
+        if matches!(config.get_output_type(), TemplateOutputType::File | TemplateOutputType::Inject) && files.len() > 1 {
+            return Err(TemplateLoadError::TooManyMatchingFiles);
+        }
+
+        // Files that shouldn't be rendered are kept out of Tera, which would choke on them
+        let mut verbatim_files: Vec<PathBuf> = Vec::new();
+        let mut template_files: Vec<(PathBuf, String)> = Vec::new();
+        for (f, source) in files.iter().zip(sources.iter()) {
+            let verbatim = config.should_copy_without_render(f) || is_binary_file(source).map_err(TemplateLoadError::FileReadError)?;
+
+            if verbatim {
+                verbatim_files.push(f.clone());
+            } else {
+                template_files.push((source.clone(), f.display().to_string()));
+            }
+        }
+
+        let tera = match load_named_template_files(&template_files) {
+            Err(tera_error) => return Err(TemplateLoadError::TemplateFileLoadError(tera_error)),
+            Ok(tera) => tera,
+        };
+
+        let subtemplates = Self::load_subtemplates(&path, &config, loading)?;
+
+        Ok(Self {
+            name,
+            config_path: get_config_file_path(&path),
+            path,
+            config,
+            files,
+            sources,
+            verbatim_files,
+            tera,
+            subtemplates,
+            inline_body: None,
+        })
+    }
+
+    /// Makes a template from one in a collection of templates (the file at `collection_path`), as if
+    /// it were in its own directory at `path`. Its one file is named after the template.
+    fn from_inline_config(path: &Path, collection_path: &Path, inline_config: InlineTemplateConfig, loading: &mut Vec<PathBuf>) -> Result<Self, TemplateLoadError> {
+        let name = inline_config.get_name().to_string();
+        let body = inline_config.get_body().to_string();
+        let config = inline_config.into_config();
+
+        let file = PathBuf::from(&name);
+        if !config.should_include_file(&file) {
+            return Err(TemplateLoadError::NoMatchingFiles);
+        }
+
+        let mut verbatim_files = Vec::new();
+        let mut tera = new_tera();
+        if config.should_copy_without_render(&file) {
+            verbatim_files.push(file.clone());
+        } else if let Err(tera_error) = tera.add_raw_template(&name, &body) {
+            return Err(TemplateLoadError::TemplateFileLoadError(tera_error));
+        }
+
+        let subtemplates = Self::load_subtemplates(path, &config, loading)?;
+
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            config_path: collection_path.to_path_buf(),
+            config,
+            files: vec![file],
+            sources: vec![collection_path.to_path_buf()],
+            verbatim_files,
+            tera,
+            subtemplates,
+            inline_body: Some(body),
+        })
+    }
+
+    /// Loads the subtemplates of the template at `path`, making sure none of them leads back to it.
+    fn load_subtemplates(path: &Path, config: &TemplateConfig, loading: &mut Vec<PathBuf>) -> Result<Vec<Self>, TemplateLoadError> {
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if loading.contains(&canonical_path) {
+            let mut cycle = loading.clone();
+            cycle.push(canonical_path);
+            return Err(TemplateLoadError::SubtemplateCycle(cycle));
+        }
+        loading.push(canonical_path);
+        let mut subtemplates = Vec::with_capacity(config.get_subtemplates().len());
+        for subtemplate in config.get_subtemplates() {
+            let subtemplate_path = path.parent().unwrap_or(Path::new("")).join(subtemplate.get_template());
+            match Self::load_with_parents(&subtemplate_path, loading) {
+                Err(TemplateLoadError::SubtemplateCycle(cycle)) => return Err(TemplateLoadError::SubtemplateCycle(cycle)),
+                Err(load_error) => return Err(TemplateLoadError::SubtemplateError(subtemplate.get_template().to_string(), Box::new(load_error))),
+                Ok(template) => subtemplates.push(template),
+            };
+        }
+        loading.pop();
+
+        Ok(subtemplates)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path to the template's configuration file, or to the collection of templates (see
+    /// [TEMPLATE_COLLECTION_FILE]) if it was given inline in one.
+    pub fn get_config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Returns the path to the directory holding the template's own files, which doesn't include
+    /// any from a template it extends.
+    pub fn get_files_root(&self) -> PathBuf {
+        self.path.join(TEMPLATE_FILES_DIR)
+    }
+
+    pub fn get_config(&self) -> &TemplateConfig {
+        &self.config
+    }
+
+    pub fn get_output_type(&self) -> TemplateOutputType {
+        self.config.get_output_type()
+    }
+
+    /// Returns clones of all the (key, default) variable pairs.
+    pub fn get_variable_items(&self) -> Vec<(String, tera::Value)> {
+        self.config.get_variable_items()
+    }
+
+    /// Returns the files this template renders, relative to its files directory.
+    pub fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the files this template copies without rendering, relative to its files directory.
+    pub fn get_verbatim_files(&self) -> &[PathBuf] {
+        &self.verbatim_files
+    }
+
+    /// Returns the path the given file (relative to the files directory) is read from, or None if
+    /// the template doesn't render that file. For a template given inline, this is the collection
+    /// file.
+    pub fn get_file_source<P: AsRef<Path>>(&self, file: &P) -> Option<&Path> {
+        self.files.iter().position(|f| f == file.as_ref()).map(|i| self.sources[i].as_path())
+    }
+
+    pub fn get_tera(&self) -> &Tera {
+        &self.tera
+    }
+
+    /// Describes an error from rendering one of the template's files (named by its path relative
+    /// to the files directory), pointing at the offending expression in the file's source.
+    pub fn diagnose_render_error(&self, template_name: &str, tera_error: &tera::Error) -> RenderDiagnostic {
+        let source = match &self.inline_body {
+            Some(body) => Some(body.clone()),
+            None => self.get_file_source(&template_name).and_then(|source_path| std::fs::read_to_string(source_path).ok()),
+        };
+        diagnose_render_error(template_name, tera_error, source.as_deref())
+    }
+
+    /// Describes an error from rendering the template, if it's one that happened while rendering a
+    /// file, pointing at the offending expression in the file's source. Errors from subtemplates are
+    /// followed down to the subtemplate whose file failed to render.
+    pub fn diagnose_template_render_error(&self, render_error: &TemplateRenderError) -> Option<RenderDiagnostic> {
+        match render_error {
+            TemplateRenderError::FileRenderError(template_name, tera_error) |
+            TemplateRenderError::DirectoryRenderError(DirectoryRenderError::TemplateRenderError(template_name, tera_error)) => {
+                Some(self.diagnose_render_error(template_name, tera_error))
+            },
+            TemplateRenderError::SubtemplateRenderError(subtemplate_name, subtemplate_error) => {
+                self.get_subtemplate(subtemplate_name)
+                    .and_then(|subtemplate| subtemplate.diagnose_template_render_error(subtemplate_error))
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the output filename or directory from the configuration, which is used when no
+    /// destination is given at render time.
+    pub fn get_default_output_path(&self) -> &str {
+        let default_output = match self.config.get_output_type() {
+            TemplateOutputType::File | TemplateOutputType::Inject => self.config.get_output_filename(),
+            TemplateOutputType::Directory => self.config.get_output_directory(),
+        };
+
+        default_output.unwrap_or(DEFAULT_OUTPUT_NAME)
+    }
+
+    /// Renders the template to disk.
+    ///
+    /// Values in `context` override the variable defaults from the configuration. `destination` is
+    /// the output file (for File templates), directory (for Directory templates), or the existing
+    /// file to put the rendered snippet into (for Inject templates); if it's None,
+    /// the default from the configuration is used. Either way, the destination may itself use
+    /// variables.
+    ///
+    /// The variable values, including any defaults that weren't overridden, are validated against the
+    /// variable definitions before anything is rendered.
+    ///
+    /// The template's pre-render hooks run in the output directory (for File templates, the directory
+    /// containing the output file) once it exists, before any files are written; if one fails, the
+    /// output directory is removed again if it was created by this call. The post-render hooks run
+    /// after all the files are written.
+    pub fn render(&self, context: Option<&Context>, destination: Option<&Path>) -> Result<RenderedTemplate, TemplateRenderError> {
+        let full_context = self.get_validated_context(context)?;
+
+        // Render out the final output path, in case the user named something using a variable
+        let output_path_raw = match destination {
+            Some(destination_path) => destination_path.display().to_string(),
+            None => self.get_default_output_path().to_string(),
+        };
+        let output_path = match render_path(&output_path_raw, &full_context) {
+            Err(tera_error) => return Err(TemplateRenderError::OutputPathRenderError(output_path_raw, tera_error)),
+            Ok(output_path) => output_path,
+        };
+
+        self.render_to(context, &full_context, output_path, false)
+    }
+
+    /// Renders the template to disk inside `directory`, as is done for subtemplates: File templates
+    /// are written to their default output filename inside the directory, and Directory templates
+    /// write their files straight into it. Unlike [Self::render], the directory may already exist,
+    /// and any files already in it are replaced, or merged into for files that have a merge rule.
+    pub fn render_into(&self, context: Option<&Context>, directory: &Path) -> Result<RenderedTemplate, TemplateRenderError> {
+        let full_context = self.get_validated_context(context)?;
+
+        let output_path = match self.config.get_output_type() {
+            TemplateOutputType::File | TemplateOutputType::Inject => {
+                let output_filename_raw = self.get_default_output_path().to_string();
+                match render_path(&output_filename_raw, &full_context) {
+                    Err(tera_error) => return Err(TemplateRenderError::OutputPathRenderError(output_filename_raw, tera_error)),
+                    Ok(output_filename) => directory.join(output_filename),
+                }
+            },
+            TemplateOutputType::Directory => directory.to_path_buf(),
+        };
+
+        self.render_to(context, &full_context, output_path, true)
+    }
+
+    /// Builds the full context for rendering, with the values in `context` overriding the variable
+    /// defaults, and validates it.
+    fn get_validated_context(&self, context: Option<&Context>) -> Result<Context, TemplateRenderError> {
+        let full_context = match self.config.get_render_context(context) {
+            Err(tera_error) => return Err(TemplateRenderError::ContextError(tera_error)),
+            Ok(full_context) => full_context,
+        };
+
+        if let Err((variable_name, value_error)) = self.config.validate_context(&full_context) {
+            return Err(TemplateRenderError::InvalidVariableValue(variable_name, value_error));
+        }
+
+        Ok(full_context)
+    }
+
+    /// Renders the template to `output_path`, which is a file or directory depending on the output
+    /// type. If `into_existing` is set, a Directory template's output directory may already exist.
+    fn render_to(&self, context: Option<&Context>, full_context: &Context, output_path: PathBuf, into_existing: bool) -> Result<RenderedTemplate, TemplateRenderError> {
+        match self.config.get_output_type() {
+            TemplateOutputType::File | TemplateOutputType::Inject => {
+                let source_path = self.sources[0].clone(); // Safety: load() ensures there is exactly 1 file
+                let template_name = self.files[0].display().to_string();
+                let rendered_contents = if self.verbatim_files.is_empty() {
+                    match render_single_file(&self.tera, &self.config, &template_name, context) {
+                        Err(tera_error) => return Err(TemplateRenderError::FileRenderError(template_name, tera_error)),
+                        Ok(rendered_string) => rendered_string.into_bytes(),
+                    }
+                } else if let Some(body) = &self.inline_body {
+                    body.clone().into_bytes()
+                } else {
+                    match std::fs::read(&source_path) {
+                        Err(read_error) => return Err(TemplateRenderError::ReadError(source_path, read_error)),
+                        Ok(contents) => contents,
+                    }
+                };
+
+                // Write the rendered string to the output file
+                // -- Ensure the parent directories exist, remembering the first one that was created
+                // so it can be cleaned up if a pre-render hook fails or there's no file to inject into
+                let final_directory = match output_path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                let created_directory = final_directory.ancestors().filter(|a| !a.as_os_str().is_empty() && !a.exists()).last().map(|a| a.to_path_buf());
+                if created_directory.is_some() && let Err(mkdir_error) = std::fs::create_dir_all(&final_directory) {
+                    return Err(TemplateRenderError::CreateDirectoryError(final_directory, mkdir_error));
+                }
+
+                // -- Run the pre-render hooks
+                if let Err(hook_error) = run_hooks(self.config.get_pre_render_hooks(), full_context, &final_directory) {
+                    if let Some(created_directory) = created_directory {
+                        let _ = std::fs::remove_dir_all(created_directory);
+                    }
+                    return Err(TemplateRenderError::PreRenderHookError(hook_error));
+                }
+
+                // -- Write the rendered file, or for Inject templates, put the rendered snippet into the
+                // existing file (which is left alone if it already has the snippet), or for files that
+                // are merged, merge them into the existing file if there is one
+                let merge_strategy = self.config.get_merge_strategy(&self.files[0]).filter(|_| output_path.is_file());
+                let final_contents = match self.config.get_injection() {
+                    Some(injection) => {
+                        let existing_contents = match std::fs::read_to_string(&output_path) {
+                            Err(read_error) => {
+                                if let Some(created_directory) = created_directory {
+                                    let _ = std::fs::remove_dir_all(created_directory);
+                                }
+                                return Err(TemplateRenderError::ReadError(output_path, read_error));
+                            },
+                            Ok(existing_contents) => existing_contents,
+                        };
+                        match inject_snippet(&existing_contents, &String::from_utf8_lossy(&rendered_contents), injection) {
+                            Err(inject_error) => {
+                                if let Some(created_directory) = created_directory {
+                                    let _ = std::fs::remove_dir_all(created_directory);
+                                }
+                                return Err(TemplateRenderError::InjectError(output_path, inject_error));
+                            },
+                            Ok(injected_contents) => injected_contents.map(String::into_bytes),
+                        }
+                    },
+                    None => match merge_strategy {
+                        Some(array_strategy) => {
+                            let existing_contents = match std::fs::read_to_string(&output_path) {
+                                Err(read_error) => return Err(TemplateRenderError::ReadError(output_path, read_error)),
+                                Ok(existing_contents) => existing_contents,
+                            };
+                            match merge_documents(&output_path, &existing_contents, &String::from_utf8_lossy(&rendered_contents), array_strategy) {
+                                Err(merge_error) => return Err(TemplateRenderError::MergeError(output_path, merge_error)),
+                                Ok(merged_contents) => merged_contents.map(String::into_bytes),
+                            }
+                        },
+                        None => Some(rendered_contents),
+                    },
+                };
+
+                let mut written_files = Vec::new();
+                if let Some(final_contents) = final_contents {
+                    if let Err(write_error) = std::fs::write(&output_path, final_contents) {
+                        return Err(TemplateRenderError::WriteError(output_path, write_error));
+                    }
+                    written_files.push(output_path.clone());
+                }
+
+                // -- Render the subtemplates alongside the file
+                written_files.extend(self.render_subtemplates(full_context, &final_directory)?);
+
+                // -- Run the post-render hooks
+                if let Err(hook_error) = run_hooks(self.config.get_post_render_hooks(), full_context, &final_directory) {
+                    return Err(TemplateRenderError::PostRenderHookError(hook_error));
+                }
+
+                Ok(RenderedTemplate {
+                    output_path,
+                    written_files,
+                })
+            },
+            TemplateOutputType::Directory => {
+                let verbatim_sources: Vec<(PathBuf, PathBuf)> = self.verbatim_files.iter()
+                    .filter_map(|f| self.get_file_source(f).map(|source| (f.clone(), source.to_path_buf())))
+                    .collect();
+                let rendered_files = render_directory(&self.tera, &self.config, &self.get_files_root(), &verbatim_sources, context)
+                    .map_err(TemplateRenderError::DirectoryRenderError)?;
+                let created_output_directory = !(into_existing && output_path.is_dir());
+                if created_output_directory {
+                    create_output_directory(&output_path)
+                        .map_err(TemplateRenderError::DirectoryRenderError)?;
+                }
+
+                // If the output directory is new and a pre-render hook fails, it can all be removed
+                if let Err(hook_error) = run_hooks(self.config.get_pre_render_hooks(), full_context, &output_path) {
+                    if created_output_directory {
+                        let _ = std::fs::remove_dir_all(&output_path);
+                    }
+                    return Err(TemplateRenderError::PreRenderHookError(hook_error));
+                }
+
+                let mut written_files = write_rendered_files(&output_path, &rendered_files)
+                    .map_err(TemplateRenderError::DirectoryRenderError)?;
+                written_files.extend(self.render_subtemplates(full_context, &output_path)?);
+
+                if let Err(hook_error) = run_hooks(self.config.get_post_render_hooks(), full_context, &output_path) {
+                    return Err(TemplateRenderError::PostRenderHookError(hook_error));
+                }
+
+                Ok(RenderedTemplate {
+                    output_path,
+                    written_files,
+                })
+            },
+        }
+    }
+
+    /// Renders each subtemplate into its directory under `output_directory`. A subtemplate gets the
+    /// values from `context` for the variables it shares with this template, overridden by the
+    /// values configured for it, which may themselves use variables.
+    ///
+    /// Returns the paths of all the files written.
+    fn render_subtemplates(&self, context: &Context, output_directory: &Path) -> Result<Vec<PathBuf>, TemplateRenderError> {
+        let mut written_files = Vec::new();
+        for (subtemplate, template) in self.config.get_subtemplates().iter().zip(self.subtemplates.iter()) {
+            let subtemplate_name = subtemplate.get_template().to_string();
+
+            let mut subtemplate_context = Context::new();
+            for (variable_name, _) in template.get_variable_items() {
+                if let Some(value) = context.get(&variable_name) {
+                    subtemplate_context.insert(variable_name, value);
+                }
+            }
+            for (variable_name, value) in subtemplate.get_variables() {
+                // Rendered values are converted back to the type of the subtemplate's variable
+                let value = match value {
+                    Value::String(raw_value) if is_template_string(raw_value) => match render_one_off(raw_value, context, false) {
+                        Err(tera_error) => return Err(TemplateRenderError::SubtemplateRenderError(subtemplate_name, Box::new(TemplateRenderError::ContextError(tera_error)))),
+                        Ok(rendered_value) => template.get_config().get_variable_type(variable_name)
+                            .and_then(|variable_type| variable_type.parse_input(&rendered_value).ok())
+                            .unwrap_or(Value::String(rendered_value)),
+                    },
+                    _ => value.clone(),
+                };
+                subtemplate_context.insert(variable_name, &value);
+            }
+
+            let into = match render_path(subtemplate.get_into(), context) {
+                Err(tera_error) => return Err(TemplateRenderError::OutputPathRenderError(subtemplate.get_into().to_string(), tera_error)),
+                Ok(into) => into,
+            };
+
+            match template.render_into(Some(&subtemplate_context), &output_directory.join(into)) {
+                Err(render_error) => return Err(TemplateRenderError::SubtemplateRenderError(subtemplate_name, Box::new(render_error))),
+                Ok(rendered_template) => written_files.extend(rendered_template.written_files),
+            };
+        }
+
+        Ok(written_files)
+    }
+
+    /// Returns the templates for each of the configuration's subtemplates, in the same order.
+    pub fn get_subtemplates(&self) -> &[Template] {
+        &self.subtemplates
+    }
+
+    /// Returns the template for the first of the configuration's subtemplates with the given name.
+    pub fn get_subtemplate(&self, name: &str) -> Option<&Template> {
+        self.config.get_subtemplates().iter().zip(self.subtemplates.iter())
+            .find(|(subtemplate, _)| subtemplate.get_template() == name)
+            .map(|(_, template)| template)
+    }
+}
+
+/// Returns the path of the configuration file of the template at `path`: the first of
+/// [TEMPLATE_CONFIG_FILES] that exists, or None if there isn't one.
+pub fn find_config_file<P: AsRef<Path>>(path: &P) -> Option<PathBuf> {
+    TEMPLATE_CONFIG_FILES.iter()
+        .map(|config_file| path.as_ref().join(config_file))
+        .find(|config_path| config_path.is_file())
+}
+
+/// Returns the path of the collection of templates (see [TEMPLATE_COLLECTION_FILE]) that the template
+/// at `path` would be in, if there's no directory at `path` and the collection exists.
+pub fn find_template_collection<P: AsRef<Path>>(path: &P) -> Option<PathBuf> {
+    let path = path.as_ref();
+    if path.is_dir() {
+        return None;
+    }
+
+    path.parent()
+        .map(|templates_dir| templates_dir.join(TEMPLATE_COLLECTION_FILE))
+        .filter(|collection_path| collection_path.is_file())
+}
+
+/// Returns the path of the configuration file of the template at `path`, which is [TEMPLATE_CONFIG_FILE]
+/// if it doesn't have one, so that the error from reading it names the file expected.
+fn get_config_file_path(path: &Path) -> PathBuf {
+    find_config_file(&path).unwrap_or_else(|| path.join(TEMPLATE_CONFIG_FILE))
+}
+
+/// Parses the configuration of the template at `path`, merging in the configuration of the templates
+/// it extends. Also returns the files directories of the template and its parents, from the most
+/// distant parent to the template itself.
+///
+/// `visited` holds the templates already loaded further down the chain, to detect cycles.
+fn load_config_with_parents(path: &Path, visited: &mut Vec<PathBuf>) -> Result<(TemplateConfig, Vec<PathBuf>), TemplateLoadError> {
+    let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if visited.contains(&canonical_path) {
+        visited.push(canonical_path);
+        return Err(TemplateLoadError::InheritanceCycle(visited.clone()));
+    }
+    visited.push(canonical_path);
+
+    let mut config = match parse_config_from_file(&get_config_file_path(path)) {
+        Err(config_error) => return Err(TemplateLoadError::ConfigError(config_error)),
+        Ok(config) => config,
+    };
+
+    let mut files_roots = Vec::new();
+    if let Some(parent_name) = config.get_extends() {
+        let parent_path = path.parent().unwrap_or(Path::new("")).join(parent_name);
+        if !parent_path.is_dir() {
+            return Err(TemplateLoadError::TemplateNotFound(parent_path));
+        }
+
+        let (parent_config, parent_files_roots) = load_config_with_parents(&parent_path, visited)?;
+        config.inherit_from(&parent_config);
+        files_roots = parent_files_roots;
+    }
+    files_roots.push(path.join(TEMPLATE_FILES_DIR));
+
+    Ok((config, files_roots))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TestDirectory;
+
+    #[test]
+    pub fn load_works_for_example_templates() {
+        let template = Template::load(&"examples/rust-script").unwrap();
+        assert_eq!(template.get_name(), "rust-script");
+        assert_eq!(template.get_output_type(), TemplateOutputType::File);
+        assert_eq!(template.get_files(), &[PathBuf::from("template.rs")]);
+        assert_eq!(template.get_default_output_path(), "script.rs");
+
+        let template = Template::load(&"examples/utsusu-template").unwrap();
+        assert_eq!(template.get_name(), "utsusu-template");
+        assert_eq!(template.get_output_type(), TemplateOutputType::Directory);
+        assert_eq!(template.get_files(), &[PathBuf::from("README.md"), PathBuf::from("config.yml"), PathBuf::from("files/file.md")]);
+    }
+
+    #[test]
+    pub fn load_overlays_extended_templates() {
+        let templates_dir = TestDirectory::new("extends");
+        templates_dir.write("base/config.yml", "type: directory\noutput:\n  directory: out\ninclude: \"*\"\nvariables:\n  crate_name: base\n  edition: \"2021\"\n");
+        templates_dir.write("base/files/Cargo.toml", "{{ crate_name }} {{ edition }}");
+        templates_dir.write("base/files/README.md", "base readme");
+        templates_dir.write("child/config.yml", "extends: base\nexclude: Cargo.lock\nvariables:\n  edition: \"2024\"\n");
+        templates_dir.write("child/files/README.md", "child readme");
+        templates_dir.write("child/files/Cargo.lock", "ignored");
+        templates_dir.write("loop-a/config.yml", "extends: loop-b\n");
+        templates_dir.write("loop-b/config.yml", "extends: loop-a\n");
+
+        let child = Template::load(&templates_dir.join("child")).unwrap();
+        assert_eq!(child.get_output_type(), TemplateOutputType::Directory);
+        assert_eq!(child.get_files(), &[PathBuf::from("Cargo.toml"), PathBuf::from("README.md")]);
+        assert!(child.get_file_source(&"Cargo.toml").unwrap().ends_with("base/files/Cargo.toml"));
+        assert!(child.get_file_source(&"README.md").unwrap().ends_with("child/files/README.md"));
+
+        let context = child.get_config().get_render_context(None).unwrap();
+        assert_eq!(child.get_tera().render("Cargo.toml", &context).unwrap(), "base 2024");
+        assert_eq!(child.get_tera().render("README.md", &context).unwrap(), "child readme");
+
+        assert!(matches!(Template::load(&templates_dir.join("loop-a")), Err(TemplateLoadError::InheritanceCycle(_))));
+    }
+
+    #[test]
+    pub fn load_finds_toml_and_json_configs() {
+        let templates_dir = TestDirectory::new("config-formats");
+        templates_dir.write("toml/config.toml", "type = \"file\"\ninclude = \"README.md\"\n\n[output]\nfilename = \"README.md\"\n");
+        templates_dir.write("toml/files/README.md", "toml");
+        templates_dir.write("json/utsusu.json", "{\"type\": \"directory\", \"output\": {\"directory\": \"out\"}, \"include\": \"*\"}");
+        templates_dir.write("json/config.yml.bak", "not a config");
+        templates_dir.write("json/files/README.md", "json");
+        templates_dir.write("both/config.yml", "type: file\noutput:\n  filename: README.md\ninclude: README.md\n");
+        templates_dir.write("both/config.json", "{");
+
+        let toml_template = Template::load(&templates_dir.join("toml")).unwrap();
+        assert_eq!(toml_template.get_output_type(), TemplateOutputType::File);
+        assert_eq!(toml_template.get_config_path(), templates_dir.join("toml/config.toml"));
+        assert_eq!(Template::load(&templates_dir.join("json")).unwrap().get_output_type(), TemplateOutputType::Directory);
+        assert!(find_config_file(&templates_dir.join("both")).unwrap().ends_with("both/config.yml"));
+        assert_eq!(find_config_file(&templates_dir.join("toml/files")), None);
+    }
+
+    #[test]
+    pub fn load_finds_templates_in_collections() {
+        let templates_dir = TestDirectory::new("collection");
+        templates_dir.write(TEMPLATE_COLLECTION_FILE, r#"
+name: module
+type: file
+output:
+  filename: "{{ module }}.rs"
+body: |
+  //! The {{ module }} module
+variables:
+  module: utils
+---
+name: license
+type: file
+output:
+  filename: LICENSE
+body: "(c) {{ holder }}"
+subtemplates: [module]
+variables:
+  holder: Acme
+"#);
+
+        let output_path = templates_dir.join("rendered/LICENSE");
+        let license = Template::load(&templates_dir.join("license")).unwrap();
+        assert_eq!(license.get_files(), &[PathBuf::from("license")]);
+        assert_eq!(license.get_config_path(), templates_dir.join(TEMPLATE_COLLECTION_FILE));
+        assert_eq!(license.render(None, Some(&output_path)).unwrap().written_files.len(), 2);
+        assert_eq!(std::fs::read_to_string(&output_path).unwrap(), "(c) Acme");
+        assert_eq!(std::fs::read_to_string(templates_dir.join("rendered/utils.rs")).unwrap(), "//! The utils module\n");
+
+        let collection = Template::load_collection(&templates_dir.join(TEMPLATE_COLLECTION_FILE)).unwrap();
+        assert_eq!(collection.iter().map(Template::get_name).collect::<Vec<_>>(), vec!["module", "license"]);
+        assert!(matches!(Template::load(&templates_dir.join("missing")), Err(TemplateLoadError::TemplateNotFound(_))));
+    }
+
+    #[test]
+    pub fn render_includes_subtemplates() {
+        let templates_dir = TestDirectory::new("subtemplates");
+        templates_dir.write("service/config.yml", "type: directory\noutput:\n  directory: out\ninclude: \"*\"\nsubtemplates:\n  - template: license\n  - template: ci\n    into: .ci\n    vars:\n      cache: \"{{ fast }}\"\nvariables:\n  holder: Acme\n  fast: true\n");
+        templates_dir.write("service/files/README.md", "{{ holder }} service");
+        templates_dir.write("license/config.yml", "type: file\noutput:\n  filename: LICENSE\ninclude: LICENSE\nvariables:\n  holder: nobody\n  year: 2024\n");
+        templates_dir.write("license/files/LICENSE", "(c) {{ year }} {{ holder }}");
+        templates_dir.write("ci/config.yml", "type: directory\noutput:\n  directory: ci\ninclude: \"*\"\nvariables:\n  cache: false\n");
+        templates_dir.write("ci/files/ci.yml", "cache: {{ cache }}{% if cache == true %} (bool){% endif %}");
+        templates_dir.write("loop/config.yml", "type: directory\noutput:\n  directory: out\ninclude: \"*\"\nsubtemplates: [loop]\n");
+        templates_dir.write("loop/files/README.md", "loop");
+
+        let output_dir = templates_dir.join("rendered");
+        let rendered = Template::load(&templates_dir.join("service")).unwrap().render(None, Some(&output_dir));
+        assert_eq!(rendered.unwrap().written_files.len(), 3);
+        assert_eq!(std::fs::read_to_string(output_dir.join("LICENSE")).unwrap(), "(c) 2024 Acme");
+        assert_eq!(std::fs::read_to_string(output_dir.join(".ci/ci.yml")).unwrap(), "cache: true (bool)");
+        assert!(matches!(Template::load(&templates_dir.join("loop")), Err(TemplateLoadError::SubtemplateCycle(_))));
+    }
+
+    #[test]
+    pub fn render_errors_are_diagnosed_in_subtemplates() {
+        let templates_dir = TestDirectory::new("subtemplate-diagnostics");
+        templates_dir.write("service/config.yml", "type: directory\noutput:\n  directory: out\ninclude: \"*\"\nsubtemplates: [license]\n");
+        templates_dir.write("service/files/README.md", "service");
+        templates_dir.write("license/config.yml", "type: file\noutput:\n  filename: LICENSE\ninclude: LICENSE\n");
+        templates_dir.write("license/files/LICENSE", "MIT\n(c) {{ holder }}");
+
+        let service = Template::load(&templates_dir.join("service")).unwrap();
+        let render_error = service.render(None, Some(&templates_dir.join("rendered"))).unwrap_err();
+        assert!(render_error.to_string().starts_with("could not render subtemplate 'license': could not render template file 'LICENSE'"));
+
+        let diagnostic = service.diagnose_template_render_error(&render_error).unwrap();
+        assert_eq!(diagnostic.get_undefined_variable(), Some("holder"));
+        assert_eq!(diagnostic.get_location(), Some((2, 8, 6)));
+    }
+
+    #[test]
+    pub fn render_cleans_up_when_there_is_nothing_to_inject_into() {
+        let templates_dir = TestDirectory::new("inject-missing-target");
+        templates_dir.write("route/config.yml", "type: inject\noutput:\n  filename: routes.rs\n  marker: routes\n  position: after\ninclude: route.rs\n");
+        templates_dir.write("route/files/route.rs", "route!(\"/\");");
+
+        let template = Template::load(&templates_dir.join("route")).unwrap();
+        let rendered = template.render(None, Some(&templates_dir.join("rendered/src/routes.rs")));
+        assert!(matches!(rendered, Err(TemplateRenderError::ReadError(_, _))));
+        assert!(!templates_dir.join("rendered").exists());
+    }
+
+    #[test]
+    pub fn load_errors_for_missing_template() {
+        assert!(matches!(Template::load(&"examples/does-not-exist"), Err(TemplateLoadError::TemplateNotFound(_))));
+    }
+}
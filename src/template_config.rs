@@ -1,22 +1,68 @@
 //! This module provides configuration specification and parsing for templates.
 
+use std::borrow::Cow;
 use std::fmt;
 use std::fs::read_to_string;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use saphyr::{LoadableYamlNode, YamlOwned, ScalarOwned};
-use globset::{Glob, GlobSet};
-
-// TODO:
-// - config string parsing fn
-// - config file parsing fn
-
+use saphyr::{LoadableYamlNode, MappingOwned, MarkedYamlOwned, YamlDataOwned, YamlEmitter, YamlOwned, ScalarOwned};
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use tera::{Map, Value};
+use crate::template_rendering::evaluate_condition;
+use crate::template_rendering::diagnostics::get_line_and_column;
+use crate::template_variable::{TemplateVariable, ValueKind, VariableType, VariableValidation, VariableValueError};
+use crate::value_conversion::{scalar_to_value, toml_to_yaml, value_to_owned_yaml, value_to_yaml};
+
+const CONFIG_KEY_EXTENDS: &str = "extends";
+const CONFIG_KEY_INLINE_NAME: &str = "name";
+const CONFIG_KEY_INLINE_BODY: &str = "body";
 const CONFIG_KEY_OUTPUT_TYPE: &str = "type";
 const CONFIG_KEY_OUTPUT_TOP_LEVEL: &str = "output";
 const CONFIG_KEY_OUTPUT_FILENAME: &str = "filename";
 const CONFIG_KEY_OUTPUT_DIRECTORY: &str = "directory";
+const CONFIG_KEY_INJECT_ANCHOR: &str = "anchor";
+const CONFIG_KEY_INJECT_MARKER: &str = "marker";
+const CONFIG_KEY_INJECT_END_ANCHOR: &str = "end_anchor";
+const CONFIG_KEY_INJECT_END_MARKER: &str = "end_marker";
+const CONFIG_KEY_INJECT_POSITION: &str = "position";
 const CONFIG_KEY_INCLUDED_FILES: &str = "include";
+const CONFIG_KEY_EXCLUDED_FILES: &str = "exclude";
+const CONFIG_KEY_COPY_WITHOUT_RENDER: &str = "copy_without_render";
+const CONFIG_KEY_FILE_CONDITIONS: &str = "files";
+const CONFIG_KEY_RENAME_RULES: &str = "rename";
+const CONFIG_KEY_MERGE: &str = "merge";
+const CONFIG_KEY_DOTFILE_PREFIX: &str = "dotfiles";
+const CONFIG_KEY_SUBTEMPLATES: &str = "subtemplates";
+const CONFIG_KEY_SUBTEMPLATE_TEMPLATE: &str = "template";
+const CONFIG_KEY_SUBTEMPLATE_INTO: &str = "into";
+const CONFIG_KEY_SUBTEMPLATE_VARIABLES: &str = "vars";
+const CONFIG_KEY_HOOKS: &str = "hooks";
+const CONFIG_KEY_HOOKS_PRE_RENDER: &str = "pre_render";
+const CONFIG_KEY_HOOKS_POST_RENDER: &str = "post_render";
 const CONFIG_KEY_VARIABLES: &str = "variables";
+const CONFIG_KEY_VARIABLE_DEFAULT: &str = "default";
+const CONFIG_KEY_VARIABLE_PROMPT: &str = "prompt";
+const CONFIG_KEY_VARIABLE_HELP: &str = "help";
+const CONFIG_KEY_VARIABLE_CHOICES: &str = "choices";
+const CONFIG_KEY_VARIABLE_MULTIPLE: &str = "multiple";
+const CONFIG_KEY_VARIABLE_VALIDATION: &str = "validate";
+const CONFIG_KEY_VARIABLE_CONDITION: &str = "when";
+const CONFIG_KEY_VARIABLE_GROUP: &str = "group";
+const CONFIG_KEY_VARIABLE_ADVANCED: &str = "advanced";
+const CONFIG_KEY_VALIDATION_REQUIRED: &str = "required";
+const CONFIG_KEY_VALIDATION_PATTERN: &str = "pattern";
+const CONFIG_KEY_VALIDATION_MIN_LENGTH: &str = "min_length";
+const CONFIG_KEY_VALIDATION_MAX_LENGTH: &str = "max_length";
+const CONFIG_KEY_VALIDATION_MIN: &str = "min";
+const CONFIG_KEY_VALIDATION_MAX: &str = "max";
+const CONFIG_KEY_VALIDATION_KIND: &str = "kind";
+
+/// Rename rules whose pattern starts with this are regexes rather than globs.
+const RENAME_REGEX_PREFIX: &str = "re:";
+
+/// The prefixes that can be used in place of a leading `.` in the names of files and directories.
+pub const DOTFILE_PREFIXES: [&str; 2] = ["dot_", "_"];
 
 
 /// Represents the different output types of a particular template
@@ -27,23 +73,272 @@ pub enum TemplateOutputType {
 
     /// The template renders a directory tree
     Directory,
+
+    /// The template renders a snippet, which is inserted into an existing file
+    Inject,
+}
+
+/// Where an Inject template puts its snippet, relative to the anchor in the target file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InjectPosition {
+    /// On the lines before the anchor line
+    Before,
+
+    /// On the lines after the anchor line
+    After,
+
+    /// In place of the lines between the anchor line and the end anchor line
+    ReplaceBetween,
+}
+
+/// Describes where an Inject template's rendered snippet goes in its target file.
+#[derive(Debug, Clone)]
+pub struct Injection {
+    /// Matches the line the snippet is placed relative to; the first matching line is used
+    anchor: Regex,
+
+    /// For [InjectPosition::ReplaceBetween], matches the line that ends the replaced section; the
+    /// first matching line after the anchor is used
+    end_anchor: Option<Regex>,
+
+    /// Where the snippet goes, relative to the anchor
+    position: InjectPosition,
+}
+
+impl Injection {
+    pub fn new(anchor: Regex, position: InjectPosition) -> Self {
+        Self {
+            anchor,
+            end_anchor: None,
+            position,
+        }
+    }
+
+    pub fn get_anchor(&self) -> &Regex {
+        &self.anchor
+    }
+
+    pub fn set_end_anchor(&mut self, end_anchor: Option<Regex>) {
+        self.end_anchor = end_anchor;
+    }
+
+    pub fn get_end_anchor(&self) -> Option<&Regex> {
+        self.end_anchor.as_ref()
+    }
+
+    pub fn get_position(&self) -> InjectPosition {
+        self.position
+    }
+}
+
+/// How arrays are combined when a rendered document is merged into an existing one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrayMergeStrategy {
+    /// The rendered array replaces the existing one
+    Replace,
+
+    /// The rendered array's items are added after the existing ones
+    Append,
+
+    /// The rendered array's items are added after the existing ones, unless they're already there
+    Union,
+}
+
+impl ArrayMergeStrategy {
+    /// Parses a strategy by its name in the configuration.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "replace" => Some(ArrayMergeStrategy::Replace),
+            "append" => Some(ArrayMergeStrategy::Append),
+            "union" => Some(ArrayMergeStrategy::Union),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the strategy in the configuration.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ArrayMergeStrategy::Replace => "replace",
+            ArrayMergeStrategy::Append => "append",
+            ArrayMergeStrategy::Union => "union",
+        }
+    }
+}
+
+/// A single include or exclude rule for the files of a template.
+#[derive(Debug, Clone)]
+struct FilePattern {
+    /// The glob, as written in the configuration (without any `!` prefix)
+    glob: Glob,
+
+    /// Matches the paths the glob applies to
+    matcher: GlobMatcher,
+
+    /// For globs ending in `/**`, matches the directory the glob covers the whole contents of
+    contents_matcher: Option<GlobMatcher>,
+
+    /// Whether a match includes the path (true) or excludes it (false)
+    included: bool,
+
+    /// Whether the pattern was given in the exclude list, where a leading `!` includes, rather than
+    /// the include list
+    listed_as_excluded: bool,
+}
+
+impl FilePattern {
+    fn new(glob: Glob, included: bool, listed_as_excluded: bool) -> Self {
+        let contents_matcher = glob.glob().strip_suffix("/**")
+            .and_then(|prefix| Glob::new(prefix).ok())
+            .map(|prefix_glob| prefix_glob.compile_matcher());
+
+        Self {
+            matcher: glob.compile_matcher(),
+            contents_matcher,
+            glob,
+            included,
+            listed_as_excluded,
+        }
+    }
+
+    /// Returns the pattern as it's written in the include list (or the exclude list, if
+    /// `in_exclude_list` is set): the glob, with a leading `!` if it does the opposite of what the
+    /// list does, or a `\` before a leading `!` otherwise.
+    fn get_config_string(&self, in_exclude_list: bool) -> String {
+        match (self.included != in_exclude_list, self.glob.glob().starts_with('!')) {
+            (false, _) => format!("!{}", self.glob.glob()),
+            (true, true) => format!("\\{}", self.glob.glob()),
+            (true, false) => self.glob.glob().to_string(),
+        }
+    }
+
+    /// Returns whether this pattern could match anything inside `directory`, going by the literal
+    /// text at the start of the glob.
+    fn could_match_inside(&self, directory: &Path) -> bool {
+        let glob_str = self.glob.glob();
+        let literal_prefix = &glob_str[..glob_str.find(['*', '?', '[', '{', '\\']).unwrap_or(glob_str.len())];
+        let directory_prefix = format!("{}/", directory.display());
+        literal_prefix.starts_with(&directory_prefix) || directory_prefix.starts_with(literal_prefix)
+    }
+}
+
+/// Matches the source paths a rename rule applies to.
+#[derive(Debug, Clone)]
+pub enum RenamePattern {
+    /// Matches a file, or a directory (applying to everything inside it)
+    Glob(GlobMatcher),
+
+    /// Matches part of a path, which is replaced by the rule's target
+    Regex(Regex),
+}
+
+impl RenamePattern {
+    /// Returns the pattern as it's written in the configuration, with regexes prefixed by `re:`.
+    pub fn get_config_string(&self) -> String {
+        match self {
+            RenamePattern::Glob(matcher) => matcher.glob().glob().to_string(),
+            RenamePattern::Regex(regex) => format!("{}{}", RENAME_REGEX_PREFIX, regex.as_str()),
+        }
+    }
+}
+
+/// Another template rendered as a component of this one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subtemplate {
+    /// The name of the template, relative to the directory containing this template
+    template: String,
+
+    /// The directory to render the template into, relative to this template's output directory. It
+    /// may use variables.
+    into: String,
+
+    /// Values for the subtemplate's variables, which may use this template's variables
+    variables: Vec<(String, Value)>,
+}
+
+impl Subtemplate {
+    /// Creates a subtemplate rendered into the output directory, without any variable mappings.
+    pub fn new(template: String) -> Self {
+        Self {
+            template,
+            into: ".".to_string(),
+            variables: Vec::new(),
+        }
+    }
+
+    pub fn get_template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn set_into(&mut self, into: String) {
+        self.into = into;
+    }
+
+    pub fn get_into(&self) -> &str {
+        &self.into
+    }
+
+    /// Sets the value passed to the subtemplate for one of its variables. String values may use this
+    /// template's variables.
+    pub fn add_variable(&mut self, variable_name: String, value: Value) {
+        self.variables.push((variable_name, value));
+    }
+
+    pub fn get_variables(&self) -> &[(String, Value)] {
+        &self.variables
+    }
 }
 
 /// Contains the configuration for a particular template.
 #[derive(Clone)]
 pub struct TemplateConfig {
 
-    /// The glob matching patterns for files that should be included in the rendered output
-    included_file_patterns: GlobSet,
+    /// The include and exclude rules for files that should be rendered, in the order they apply.
+    /// As with gitignore files, the last rule that matches a path decides whether it's included.
+    file_patterns: Vec<FilePattern>,
+
+    /// The glob matching patterns for files that are copied as-is instead of being rendered
+    copy_without_render_patterns: Vec<GlobMatcher>,
+
+    /// Conditions that decide whether the files matching a glob are rendered at all, in the same
+    /// form as a variable's `when`
+    file_conditions: Vec<(GlobMatcher, String)>,
+
+    /// Rules rewriting where files are written to, relative to the output directory, as (pattern,
+    /// target) pairs; the first rule that matches a file applies
+    rename_rules: Vec<(RenamePattern, String)>,
+
+    /// The prefix that stands in for a leading `.` in file and directory names, if any
+    dotfile_prefix: Option<String>,
+
+    /// The files that are merged into an existing YAML, TOML, or JSON file at their output path
+    /// instead of replacing it, with how arrays are combined; the first glob that matches applies
+    merge_patterns: Vec<(GlobMatcher, ArrayMergeStrategy)>,
 
-    /// This maps variable names to default values.
-    variables: HashMap<String, String>,
+    /// Other templates rendered into this template's output
+    subtemplates: Vec<Subtemplate>,
+
+    /// Shell commands to run in the output directory before any files are written
+    pre_render_hooks: Vec<String>,
+
+    /// Shell commands to run in the output directory after all the files are written
+    post_render_hooks: Vec<String>,
+
+    /// The definitions of the template's variables, in the order they were declared.
+    variables: Vec<TemplateVariable>,
+
+    /// The template this one extends, if any, relative to the directory containing this template.
+    extends: Option<String>,
+
+    /// Whether the output type, filename, and directory are left out, to be taken from the parent
+    /// template.
+    inherits_output: bool,
 
     /// What this template outputs when it does rendering: a file, or a directory tree.
     output_type: TemplateOutputType,
 
     // Output::Filename: string; the default name of the file to write the rendered file template to
     /// The filename to render this template to, if the output type is [TemplateOutputType::File],
+    /// or to inject the rendered snippet into, if the output type is [TemplateOutputType::Inject],
     /// otherwise None.
     output_filename: Option<String>,
 
@@ -51,49 +346,415 @@ pub struct TemplateConfig {
     /// The directory to render this template to, if the output type is
     /// [TemplateOutputType::Directory], otherwise None.
     output_directory: Option<String>,
+
+    /// Where the rendered snippet goes in the target file, if the output type is
+    /// [TemplateOutputType::Inject], otherwise None.
+    injection: Option<Injection>,
 }
 
 impl TemplateConfig {
+    /// Starts building a template configuration, which is checked in the same way as a parsed one
+    /// when it's built. See [TemplateConfigBuilder].
+    pub fn builder() -> TemplateConfigBuilder {
+        TemplateConfigBuilder::new()
+    }
+
     /// Creates a new, empty template configuration.
     pub fn new() -> Self {
         Self {
-            included_file_patterns: GlobSet::empty(),
-            variables: HashMap::new(),
+            file_patterns: Vec::new(),
+            copy_without_render_patterns: Vec::new(),
+            file_conditions: Vec::new(),
+            rename_rules: Vec::new(),
+            dotfile_prefix: None,
+            merge_patterns: Vec::new(),
+            subtemplates: Vec::new(),
+            pre_render_hooks: Vec::new(),
+            post_render_hooks: Vec::new(),
+            variables: Vec::new(),
+            extends: None,
+            inherits_output: false,
             output_type: TemplateOutputType::File,
             output_filename: None,
             output_directory: None,
+            injection: None,
         }
     }
 
-    /// Adds a pattern to the list of matching patterns for files that this template will render.
-    pub fn update_included_file_patterns(&mut self, globset: GlobSet) {
-        self.included_file_patterns = globset;
+    /// Adds a pattern matching files that this template will render. It takes priority over every
+    /// pattern added before it.
+    pub fn add_included_file_pattern(&mut self, glob: Glob) {
+        self.file_patterns.push(FilePattern::new(glob, true, false));
+    }
+
+    /// Adds a pattern matching files that this template will not render, even if an earlier pattern
+    /// includes them. It takes priority over every pattern added before it.
+    ///
+    /// A pattern that matches a directory excludes everything inside it.
+    pub fn add_excluded_file_pattern(&mut self, glob: Glob) {
+        self.file_patterns.push(FilePattern::new(glob, false, true));
+    }
+
+    /// Adds an include or exclude pattern, remembering which list of the configuration it was given
+    /// in so it's written back out there.
+    fn add_listed_file_pattern(&mut self, glob: Glob, included: bool, listed_as_excluded: bool) {
+        self.file_patterns.push(FilePattern::new(glob, included, listed_as_excluded));
     }
 
     /// Returns whether the given file should be rendered by this template.
+    ///
+    /// Files inside an excluded directory are never rendered; otherwise, the last pattern that
+    /// matches the file decides, and files that no pattern matches aren't rendered.
     pub fn should_include_file<P: AsRef<Path>>(&self, path: &P) -> bool {
-        self.included_file_patterns.is_match(path)
+        let path = path.as_ref();
+        if path.ancestors().skip(1).any(|ancestor| !ancestor.as_os_str().is_empty() && self.is_directory_excluded(ancestor)) {
+            return false;
+        }
+
+        self.file_patterns.iter()
+            .rfind(|pattern| pattern.matcher.is_match(path))
+            .is_some_and(|pattern| pattern.included)
+    }
+
+    /// Returns whether a directory (relative to the files directory) might contain files that should
+    /// be rendered by this template, i.e. whether it's worth looking inside.
+    pub fn should_traverse_directory<P: AsRef<Path>>(&self, path: &P) -> bool {
+        let path = path.as_ref();
+        if self.is_directory_excluded(path) {
+            return false;
+        }
+
+        // A `dir/**` exclusion covers everything inside the directory, unless a later pattern could
+        // include something in there again
+        match self.file_patterns.iter().rposition(|pattern| !pattern.included && pattern.contents_matcher.as_ref().is_some_and(|m| m.is_match(path))) {
+            Some(position) => self.file_patterns[position + 1..].iter().any(|pattern| pattern.included && pattern.could_match_inside(path)),
+            None => true,
+        }
+    }
+
+    /// Adds a pattern matching files that are copied to the output as-is, instead of being rendered.
+    /// Their paths are still rendered.
+    pub fn add_copy_without_render_pattern(&mut self, glob: Glob) {
+        self.copy_without_render_patterns.push(glob.compile_matcher());
+    }
+
+    /// Returns whether the given file should be copied as-is instead of being rendered.
+    pub fn should_copy_without_render<P: AsRef<Path>>(&self, path: &P) -> bool {
+        self.copy_without_render_patterns.iter().any(|pattern| pattern.is_match(path))
+    }
+
+    /// Adds a condition for the files matching a glob (or inside a directory matching it), which are
+    /// only rendered when the condition is true. See [evaluate_condition] for the condition format.
+    pub fn add_file_condition(&mut self, glob: Glob, condition: String) {
+        self.file_conditions.push((glob.compile_matcher(), condition));
+    }
+
+    /// Returns whether the given file should be rendered, given the variable values in `context`:
+    /// false if any condition that applies to the file is false, and true otherwise.
+    pub fn is_file_enabled<P: AsRef<Path>>(&self, path: &P, context: &tera::Context) -> tera::Result<bool> {
+        let path = path.as_ref();
+        for (pattern, condition) in self.file_conditions.iter() {
+            let applies = path.ancestors().any(|ancestor| !ancestor.as_os_str().is_empty() && pattern.is_match(ancestor));
+            if applies && !evaluate_condition(condition, context)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Adds a rule rewriting the output path of the files matching `pattern`, after all the existing
+    /// rules. The target may use variables, and for regex patterns, capture groups (`$1`, `${name}`).
+    pub fn add_rename_rule(&mut self, pattern: RenamePattern, target: String) {
+        self.rename_rules.push((pattern, target));
+    }
+
+    /// Sets the prefix that stands in for a leading `.` in file and directory names, e.g. `dot_` to
+    /// write `dot_gitignore` as `.gitignore`.
+    pub fn set_dotfile_prefix(&mut self, prefix: Option<String>) {
+        self.dotfile_prefix = prefix;
+    }
+
+    pub fn get_dotfile_prefix(&self) -> Option<&str> {
+        self.dotfile_prefix.as_deref()
+    }
+
+    /// Adds a pattern matching files that are merged into an existing file at their output path,
+    /// after all the existing patterns.
+    pub fn add_merge_pattern(&mut self, glob: Glob, array_strategy: ArrayMergeStrategy) {
+        self.merge_patterns.push((glob.compile_matcher(), array_strategy));
     }
 
-    /// Returns clones of all the (key, default) variable pairs.
-    pub fn get_variable_items(&self) -> Vec<(String, String)> {
-        self.variables.iter().map(|(k,v)| (k.clone(), v.clone())).collect()
+    /// Returns how the given file is merged into an existing file at its output path, or None if it
+    /// replaces it.
+    pub fn get_merge_strategy<P: AsRef<Path>>(&self, path: &P) -> Option<ArrayMergeStrategy> {
+        self.merge_patterns.iter()
+            .find(|(pattern, _)| pattern.is_match(path))
+            .map(|(_, array_strategy)| *array_strategy)
+    }
+
+    /// Returns the unrendered output path, relative to the output directory, for a file at `path`
+    /// relative to the files directory.
+    ///
+    /// The first rename rule that matches the file applies. Otherwise, if a dotfile prefix is set, it
+    /// is replaced by a `.` at the start of every file and directory name it begins (as long as the
+    /// rest of the name is neither empty nor begins with `_`, so `__init__.py` is left alone).
+    pub fn get_output_path<P: AsRef<Path>>(&self, path: &P) -> PathBuf {
+        let path = path.as_ref();
+        for (pattern, target) in self.rename_rules.iter() {
+            match pattern {
+                RenamePattern::Glob(matcher) => {
+                    let matching_ancestor = path.ancestors().find(|ancestor| !ancestor.as_os_str().is_empty() && matcher.is_match(ancestor));
+                    if let Some(ancestor) = matching_ancestor {
+                        let remainder = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+                        return PathBuf::from(target).join(remainder);
+                    }
+                },
+                RenamePattern::Regex(regex) => {
+                    let path_string = path.display().to_string();
+                    if regex.is_match(&path_string) {
+                        return PathBuf::from(regex.replace(&path_string, target.as_str()).into_owned());
+                    }
+                },
+            };
+        }
+
+        match &self.dotfile_prefix {
+            Some(prefix) => path.iter()
+                .map(|component| {
+                    let name = component.to_string_lossy();
+                    match name.strip_prefix(prefix.as_str()) {
+                        Some(rest) if !rest.is_empty() && !rest.starts_with('_') => format!(".{}", rest),
+                        _ => name.to_string(),
+                    }
+                })
+                .collect(),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Adds another template to render into this template's output, after all the existing ones.
+    pub fn add_subtemplate(&mut self, subtemplate: Subtemplate) {
+        self.subtemplates.push(subtemplate);
+    }
+
+    pub fn get_subtemplates(&self) -> &[Subtemplate] {
+        &self.subtemplates
+    }
+
+    /// Adds a shell command to run in the output directory before any files are written, after all
+    /// the existing ones. The command may use variables.
+    pub fn add_pre_render_hook(&mut self, command: String) {
+        self.pre_render_hooks.push(command);
+    }
+
+    pub fn get_pre_render_hooks(&self) -> &[String] {
+        &self.pre_render_hooks
+    }
+
+    /// Adds a shell command to run in the output directory after all the files are written, after
+    /// all the existing ones. The command may use variables.
+    pub fn add_post_render_hook(&mut self, command: String) {
+        self.post_render_hooks.push(command);
+    }
+
+    pub fn get_post_render_hooks(&self) -> &[String] {
+        &self.post_render_hooks
+    }
+
+    /// Sets the template this one extends, relative to the directory containing this template.
+    pub fn set_extends(&mut self, parent_template: Option<String>) {
+        self.extends = parent_template;
+    }
+
+    pub fn get_extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+
+    /// Merges in the configuration of the template this one extends:
+    /// - The parent's variables come first, and this template's definitions replace the parent's
+    ///   ones with the same name
+    /// - The parent's include and exclude globs, copy-verbatim globs, file conditions, subtemplates,
+    ///   and hooks apply before this template's, so this template's globs take priority
+    /// - This template's rename rules and merge globs are tried before the parent's
+    /// - The output configuration and dotfile prefix are taken from the parent if this template
+    ///   leaves them out
+    pub fn inherit_from(&mut self, parent: &TemplateConfig) {
+        let own_variables = std::mem::replace(&mut self.variables, parent.variables.clone());
+        for variable in own_variables {
+            self.add_template_variable(variable);
+        }
+
+        self.file_patterns = parent.file_patterns.iter().cloned().chain(self.file_patterns.drain(..)).collect();
+        self.copy_without_render_patterns = parent.copy_without_render_patterns.iter().cloned().chain(self.copy_without_render_patterns.drain(..)).collect();
+        self.file_conditions = parent.file_conditions.iter().cloned().chain(self.file_conditions.drain(..)).collect();
+        self.rename_rules.extend(parent.rename_rules.iter().cloned());
+        self.merge_patterns.extend(parent.merge_patterns.iter().cloned());
+        self.subtemplates = parent.subtemplates.iter().cloned().chain(self.subtemplates.drain(..)).collect();
+        self.pre_render_hooks = parent.pre_render_hooks.iter().cloned().chain(self.pre_render_hooks.drain(..)).collect();
+        self.post_render_hooks = parent.post_render_hooks.iter().cloned().chain(self.post_render_hooks.drain(..)).collect();
+
+        if self.dotfile_prefix.is_none() {
+            self.dotfile_prefix = parent.dotfile_prefix.clone();
+        }
+
+        if self.inherits_output {
+            self.output_type = parent.output_type;
+            self.output_filename = parent.output_filename.clone();
+            self.output_directory = parent.output_directory.clone();
+            self.injection = parent.injection.clone();
+            self.inherits_output = false;
+        }
+    }
+
+    /// Returns whether the last pattern that matches the directory itself excludes it.
+    fn is_directory_excluded(&self, path: &Path) -> bool {
+        self.file_patterns.iter()
+            .rfind(|pattern| pattern.matcher.is_match(path))
+            .is_some_and(|pattern| !pattern.included)
+    }
+
+    /// Returns clones of all the (key, default) variable pairs, in declaration order.
+    pub fn get_variable_items(&self) -> Vec<(String, Value)> {
+        self.variables.iter().map(|v| (v.get_name().to_string(), v.get_default().clone())).collect()
+    }
+
+    /// Returns the definitions of all the variables, in the order they should be prompted for and
+    /// resolved: grouped by their group (in order of each group's first variable), and otherwise in
+    /// declaration order, except that every variable comes after the variables its computed default
+    /// and condition reference. A group that other groups reference is moved ahead of them, so each
+    /// group's variables stay together.
+    ///
+    /// If the references are cyclic, the variables are returned in declaration order instead;
+    /// [Self::find_variable_cycle] reports such cycles.
+    pub fn get_variables(&self) -> Vec<&TemplateVariable> {
+        match self.order_variables() {
+            Ok(ordered) => ordered,
+            Err(_) => self.variables.iter().collect(),
+        }
+    }
+
+    /// Returns the names of the variables forming a cycle of references between computed defaults and
+    /// conditions, if there is one.
+    pub fn find_variable_cycle(&self) -> Option<Vec<String>> {
+        self.order_variables().err()
+    }
+
+    /// Returns the groups (with None standing for the ungrouped variables) forming a cycle, where
+    /// each group has a variable whose computed default or condition references a variable in the
+    /// next, if there is one. The variables of such groups can't be prompted for a group at a time.
+    pub fn find_variable_group_cycle(&self) -> Option<Vec<Option<String>>> {
+        self.order_variable_groups().err().map(|cycle| cycle.into_iter().map(|g| g.map(str::to_string)).collect())
+    }
+
+    /// Returns the variables each variable references, leaving out any names that aren't variables.
+    fn get_variable_dependencies(&self) -> HashMap<&str, Vec<String>> {
+        self.variables.iter()
+            .map(|variable| {
+                let dependencies = variable.get_referenced_names().into_iter().filter(|n| self.get_variable(n).is_some()).collect();
+                (variable.get_name(), dependencies)
+            })
+            .collect()
+    }
+
+    /// Returns the groups of the variables (with None standing for the ungrouped variables), in order
+    /// of each group's first declared variable.
+    fn get_variable_groups(&self) -> Vec<Option<&str>> {
+        let mut groups: Vec<Option<&str>> = Vec::new();
+        for variable in self.variables.iter() {
+            if !groups.contains(&variable.get_group()) {
+                groups.push(variable.get_group());
+            }
+        }
+        groups
+    }
+
+    /// Orders the variable groups so that each one comes after the groups its variables reference,
+    /// with ties broken by declaration order. This way, every group can be prompted for in one go.
+    ///
+    /// Returns the groups forming a cycle if there is one.
+    fn order_variable_groups(&self) -> Result<Vec<Option<&str>>, Vec<Option<&str>>> {
+        let dependencies = self.get_variable_dependencies();
+        let groups = self.get_variable_groups();
+        let group_dependencies: HashMap<Option<&str>, Vec<Option<&str>>> = groups.iter()
+            .map(|group| {
+                let mut dependency_groups: Vec<Option<&str>> = Vec::new();
+                for variable in self.variables.iter().filter(|v| v.get_group() == *group) {
+                    for dependency_group in dependencies[variable.get_name()].iter().filter_map(|d| self.get_variable(d)).map(|d| d.get_group()) {
+                        if dependency_group != *group && !dependency_groups.contains(&dependency_group) {
+                            dependency_groups.push(dependency_group);
+                        }
+                    }
+                }
+                (*group, dependency_groups)
+            })
+            .collect();
+
+        order_by_dependencies(&groups, |group| &group_dependencies[&group])
+    }
+
+    /// Orders the variables so that each one comes after its dependencies, with ties broken by group
+    /// and then declaration order. Groups are kept together, in the order given by
+    /// [Self::order_variable_groups] (or declaration order if the groups form a cycle).
+    ///
+    /// Returns the variables forming a cycle if there is one.
+    fn order_variables(&self) -> Result<Vec<&TemplateVariable>, Vec<String>> {
+        let dependencies: HashMap<&str, Vec<&str>> = self.get_variable_dependencies().into_iter()
+            .map(|(name, variable_dependencies)| {
+                let variable_dependencies = variable_dependencies.iter().filter_map(|d| self.get_variable(d)).map(|d| d.get_name()).collect();
+                (name, variable_dependencies)
+            })
+            .collect();
+
+        let groups = self.order_variable_groups().unwrap_or_else(|_| self.get_variable_groups());
+        let mut prioritized: Vec<&str> = self.variables.iter().map(|v| v.get_name()).collect();
+        prioritized.sort_by_key(|name| groups.iter().position(|g| *g == self.get_variable(name).unwrap().get_group()));
+
+        match order_by_dependencies(&prioritized, |name| &dependencies[name]) {
+            Ok(ordered) => Ok(ordered.into_iter().map(|name| self.get_variable(name).unwrap()).collect()),
+            Err(cycle) => Err(cycle.into_iter().map(str::to_string).collect()),
+        }
+    }
+
+    pub fn get_variable(&self, variable_name: &str) -> Option<&TemplateVariable> {
+        self.variables.iter().find(|v| v.get_name() == variable_name)
+    }
+
+    /// Returns the declared type of a variable, or None if there is no such variable.
+    pub fn get_variable_type(&self, variable_name: &str) -> Option<VariableType> {
+        self.get_variable(variable_name).map(|v| v.get_type())
     }
 
     /// Adds or updates a variable to have a particular default value, which will be used for
-    /// rendering if the invoker doesn't override it at render time.
+    /// rendering if the invoker doesn't override it at render time. The type of the default value
+    /// determines the type of the variable.
     ///
     /// Returns the previous default value if one was set, None otherwise.
-    pub fn add_variable(&mut self, variable_name: String, default: String) -> Option<String> {
-        self.variables.insert(variable_name, default)
+    pub fn add_variable(&mut self, variable_name: String, default: Value) -> Option<Value> {
+        self.add_template_variable(TemplateVariable::new(variable_name, default)).map(|v| v.get_default().clone())
+    }
+
+    /// Adds a full variable definition after all the existing ones, or replaces the existing
+    /// definition with the same name (keeping its position).
+    ///
+    /// Returns the previous definition if there was one, None otherwise.
+    pub fn add_template_variable(&mut self, variable: TemplateVariable) -> Option<TemplateVariable> {
+        match self.variables.iter_mut().find(|v| v.get_name() == variable.get_name()) {
+            Some(existing_variable) => Some(std::mem::replace(existing_variable, variable)),
+            None => {
+                self.variables.push(variable);
+                None
+            },
+        }
     }
 
     /// Updates the output type of the template. If the type is actually changed, this will also
-    /// wipe the associated output name (filename or directory).
+    /// wipe the associated output configuration (filename, directory, or injection).
     pub fn set_output_type(&mut self, output_type: TemplateOutputType) {
         match (self.output_type, output_type) {
             (TemplateOutputType::File, TemplateOutputType::File) |
-            (TemplateOutputType::Directory, TemplateOutputType::Directory) => {},
+            (TemplateOutputType::Directory, TemplateOutputType::Directory) |
+            (TemplateOutputType::Inject, TemplateOutputType::Inject) => {},
             (TemplateOutputType::File, _) => {
                 self.output_type = output_type;
                 self.output_filename = None;
@@ -101,7 +762,12 @@ impl TemplateConfig {
             (TemplateOutputType::Directory, _) => {
                 self.output_type = output_type;
                 self.output_directory = None;
-            }
+            },
+            (TemplateOutputType::Inject, _) => {
+                self.output_type = output_type;
+                self.output_filename = None;
+                self.injection = None;
+            },
         }
     }
 
@@ -109,10 +775,11 @@ impl TemplateConfig {
         self.output_type
     }
 
-    /// Sets the default output name for a File output template. Does nothing if the output type is
-    /// not [TemplateOutputType::File].
+    /// Sets the default output name for a File output template, or the default target file for an
+    /// Inject output template. Does nothing if the output type is not [TemplateOutputType::File] or
+    /// [TemplateOutputType::Inject].
     pub fn set_output_filename(&mut self, filename: String) {
-        if self.output_type == TemplateOutputType::File {
+        if matches!(self.output_type, TemplateOutputType::File | TemplateOutputType::Inject) {
             self.output_filename = Some(filename);
         }
     }
@@ -133,36 +800,504 @@ impl TemplateConfig {
         self.output_directory.as_deref()
     }
 
-    pub fn get_render_context(&self) -> tera::Context {
+    /// Sets where the rendered snippet of an Inject output template goes. Does nothing if the output
+    /// type is not [TemplateOutputType::Inject].
+    pub fn set_injection(&mut self, injection: Injection) {
+        if self.output_type == TemplateOutputType::Inject {
+            self.injection = Some(injection);
+        }
+    }
+
+    pub fn get_injection(&self) -> Option<&Injection> {
+        self.injection.as_ref()
+    }
+
+    /// Checks every value in `context` that belongs to a variable of this template against that
+    /// variable's definition: that it is one of the variable's choices, and satisfies its
+    /// validation rules. Variables missing from the context, or whose condition is false (or can't be
+    /// evaluated), are not checked.
+    ///
+    /// Returns the name of the first variable with an invalid value, along with the reason.
+    pub fn validate_context(&self, context: &tera::Context) -> Result<(), (String, VariableValueError)> {
+        for variable in self.get_variables() {
+            if let Some(value) = context.get(variable.get_name())
+                && variable.is_enabled(context).unwrap_or(false)
+                && let Err(value_error) = variable.validate_value(value)
+            {
+                return Err((variable.get_name().to_string(), value_error));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the context used for rendering: every variable takes its value from `overrides` if it is
+    /// there, and its default otherwise. Computed defaults and conditions are evaluated in dependency
+    /// order, so they see the final values of the variables they reference. Variables whose condition
+    /// is false always take their default. Any other values in `overrides` are included as well.
+    pub fn get_render_context(&self, overrides: Option<&tera::Context>) -> tera::Result<tera::Context> {
         let mut context = tera::Context::new();
 
-        for (k, v) in self.variables.iter() {
-            context.insert(k, v);
+        for variable in self.get_variables() {
+            let enabled = match variable.is_enabled(&context) {
+                Ok(enabled) => enabled,
+                Err(tera_error) => return Err(tera::Error::chain(format!("Failed to evaluate the condition of variable '{}'", variable.get_name()), tera_error)),
+            };
+
+            match overrides.and_then(|o| o.get(variable.get_name())).filter(|_| enabled) {
+                Some(override_value) => context.insert(variable.get_name(), override_value),
+                None => {
+                    match variable.resolve_default(&context) {
+                        Ok(default) => context.insert(variable.get_name(), &default),
+                        Err(tera_error) => return Err(tera::Error::chain(format!("Failed to render the default value of variable '{}'", variable.get_name()), tera_error)),
+                    };
+                },
+            };
+        }
+
+        // Include any extra values, without clobbering the variables resolved above
+        if let Some(override_context) = overrides {
+            let mut extra_context = override_context.clone();
+            for variable in self.variables.iter() {
+                extra_context.remove(variable.get_name());
+            }
+            context.extend(extra_context);
+        }
+
+        Ok(context)
+    }
+
+    /// Returns the configuration as it's laid out in a configuration file, with globs and regexes as
+    /// they were written. Parsing the result (e.g. from [Self::to_yaml_string]) gives back an
+    /// equivalent configuration.
+    ///
+    /// Marker anchors for Inject templates are written as the equivalent regex, and optional values
+    /// that were left out are written out with their defaults.
+    pub fn to_value(&self) -> Value {
+        let mut config_map = Map::new();
+        if let Some(parent_template) = &self.extends {
+            config_map.insert(CONFIG_KEY_EXTENDS.to_string(), Value::from(parent_template.as_str()));
+        }
+
+        // - Output configuration -- Left out if it's taken from the parent template
+        if !self.inherits_output {
+            let output_type = match self.output_type {
+                TemplateOutputType::File => "file",
+                TemplateOutputType::Directory => "directory",
+                TemplateOutputType::Inject => "inject",
+            };
+            config_map.insert(CONFIG_KEY_OUTPUT_TYPE.to_string(), Value::from(output_type));
+
+            let mut output_map = Map::new();
+            if let Some(filename) = &self.output_filename {
+                output_map.insert(CONFIG_KEY_OUTPUT_FILENAME.to_string(), Value::from(filename.as_str()));
+            }
+            if let Some(directory) = &self.output_directory {
+                output_map.insert(CONFIG_KEY_OUTPUT_DIRECTORY.to_string(), Value::from(directory.as_str()));
+            }
+            if let Some(injection) = &self.injection {
+                insert_injection(&mut output_map, injection);
+            }
+            config_map.insert(CONFIG_KEY_OUTPUT_TOP_LEVEL.to_string(), Value::Object(output_map));
+        }
+
+        // - Files -- The exclude list applies after the include list, so patterns from the exclude
+        // list can only go back there if they all come last; otherwise everything goes in the include
+        // list, so the patterns stay in order
+        if !self.file_patterns.is_empty() || self.extends.is_none() {
+            let first_excluded = self.file_patterns.iter().position(|pattern| pattern.listed_as_excluded).unwrap_or(self.file_patterns.len());
+            let (included_patterns, excluded_patterns) = match self.file_patterns[first_excluded..].iter().all(|pattern| pattern.listed_as_excluded) {
+                true => self.file_patterns.split_at(first_excluded),
+                false => (self.file_patterns.as_slice(), &[][..]),
+            };
+
+            let included_globs = included_patterns.iter().map(|pattern| Value::from(pattern.get_config_string(false))).collect();
+            config_map.insert(CONFIG_KEY_INCLUDED_FILES.to_string(), Value::Array(included_globs));
+            if !excluded_patterns.is_empty() {
+                let excluded_globs = excluded_patterns.iter().map(|pattern| Value::from(pattern.get_config_string(true))).collect();
+                config_map.insert(CONFIG_KEY_EXCLUDED_FILES.to_string(), Value::Array(excluded_globs));
+            }
+        }
+        if !self.copy_without_render_patterns.is_empty() {
+            let verbatim_globs = self.copy_without_render_patterns.iter().map(|pattern| Value::from(pattern.glob().glob())).collect();
+            config_map.insert(CONFIG_KEY_COPY_WITHOUT_RENDER.to_string(), Value::Array(verbatim_globs));
+        }
+        if !self.file_conditions.is_empty() {
+            let conditions = self.file_conditions.iter().map(|(pattern, condition)| (pattern.glob().glob().to_string(), Value::from(condition.as_str()))).collect();
+            config_map.insert(CONFIG_KEY_FILE_CONDITIONS.to_string(), Value::Object(conditions));
+        }
+        if !self.rename_rules.is_empty() {
+            let rename_rules = self.rename_rules.iter().map(|(pattern, target)| (pattern.get_config_string(), Value::from(target.as_str()))).collect();
+            config_map.insert(CONFIG_KEY_RENAME_RULES.to_string(), Value::Object(rename_rules));
+        }
+        if !self.merge_patterns.is_empty() {
+            let merge_rules = self.merge_patterns.iter().map(|(pattern, array_strategy)| (pattern.glob().glob().to_string(), Value::from(array_strategy.get_name()))).collect();
+            config_map.insert(CONFIG_KEY_MERGE.to_string(), Value::Object(merge_rules));
+        }
+        if let Some(prefix) = &self.dotfile_prefix {
+            config_map.insert(CONFIG_KEY_DOTFILE_PREFIX.to_string(), Value::from(prefix.as_str()));
+        }
+
+        // - Subtemplates and hooks
+        if !self.subtemplates.is_empty() {
+            let subtemplates = self.subtemplates.iter().map(subtemplate_to_value).collect();
+            config_map.insert(CONFIG_KEY_SUBTEMPLATES.to_string(), Value::Array(subtemplates));
+        }
+        if !self.pre_render_hooks.is_empty() || !self.post_render_hooks.is_empty() {
+            let mut hooks_map = Map::new();
+            for (hook_key, commands) in [(CONFIG_KEY_HOOKS_PRE_RENDER, &self.pre_render_hooks), (CONFIG_KEY_HOOKS_POST_RENDER, &self.post_render_hooks)] {
+                if !commands.is_empty() {
+                    hooks_map.insert(hook_key.to_string(), Value::Array(commands.iter().map(|command| Value::from(command.as_str())).collect()));
+                }
+            }
+            config_map.insert(CONFIG_KEY_HOOKS.to_string(), Value::Object(hooks_map));
+        }
+
+        // - Variables -- In declaration order, and in the short form where that's all they need
+        if !self.variables.is_empty() {
+            let variables = self.variables.iter().map(|variable| (variable.get_name().to_string(), variable_to_value(variable))).collect();
+            config_map.insert(CONFIG_KEY_VARIABLES.to_string(), Value::Object(variables));
         }
 
-        context
+        Value::Object(config_map)
+    }
+
+    /// Writes out the configuration as YAML, as it would be in a `config.yml` file. See
+    /// [Self::to_value] for how it's laid out.
+    pub fn to_yaml_string(&self) -> String {
+        let mut yaml = String::new();
+        let mut emitter = YamlEmitter::new(&mut yaml);
+        emitter.multiline_strings(true);
+
+        // Safety: writing to a String can't fail, and the document has no aliases or tags
+        emitter.dump(&value_to_yaml(&self.to_value())).unwrap();
+
+        let mut yaml = yaml.strip_prefix("---\n").map(|body| body.to_string()).unwrap_or(yaml);
+        yaml.push('\n');
+        yaml
+    }
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl fmt::Debug for TemplateConfig {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// Globs are shown as they were written, rather than as their compiled matchers
+		let get_globs = |patterns: &[GlobMatcher]| patterns.iter().map(|pattern| pattern.glob().glob().to_string()).collect::<Vec<_>>();
 		f.debug_struct("TemplateConfig")
+		 .field("extends", &self.extends)
 		 .field("output_type", &self.output_type)
 		 .field("output_filename", &self.output_filename)
 		 .field("output_directory", &self.output_directory)
+		 .field("injection", &self.injection)
+		 .field("file_patterns", &self.file_patterns.iter().map(|pattern| pattern.get_config_string(false)).collect::<Vec<_>>())
+		 .field("copy_without_render_patterns", &get_globs(&self.copy_without_render_patterns))
+		 .field("file_conditions", &self.file_conditions.iter().map(|(pattern, condition)| (pattern.glob().glob(), condition)).collect::<Vec<_>>())
+		 .field("rename_rules", &self.rename_rules.iter().map(|(pattern, target)| (pattern.get_config_string(), target)).collect::<Vec<_>>())
+		 .field("merge_patterns", &self.merge_patterns.iter().map(|(pattern, array_strategy)| (pattern.glob().glob(), array_strategy)).collect::<Vec<_>>())
+		 .field("dotfile_prefix", &self.dotfile_prefix)
+		 .field("subtemplates", &self.subtemplates)
+		 .field("pre_render_hooks", &self.pre_render_hooks)
+		 .field("post_render_hooks", &self.post_render_hooks)
 		 .field("variables", &self.variables)
 		 .finish()
 	}
 }
 
+/// Orders `items` so that each one comes after its dependencies, otherwise keeping their order.
+///
+/// Returns the items forming a cycle if there is one.
+fn order_by_dependencies<'a, T: Copy + PartialEq + 'a>(items: &[T], get_dependencies: impl Fn(T) -> &'a [T]) -> Result<Vec<T>, Vec<T>> {
+    let mut ordered: Vec<T> = Vec::with_capacity(items.len());
+    while ordered.len() < items.len() {
+        let next_item = items.iter().find(|item| !ordered.contains(item) && get_dependencies(**item).iter().all(|d| ordered.contains(d)));
+        match next_item {
+            Some(item) => ordered.push(*item),
+            None => {
+                // Every unresolved item is waiting on another unresolved item, so following those
+                // dependencies from any of them has to loop back around eventually
+                let mut path: Vec<T> = Vec::new();
+                let mut current = *items.iter().find(|item| !ordered.contains(item)).unwrap();
+                while !path.contains(&current) {
+                    path.push(current);
+                    current = *get_dependencies(current).iter().find(|d| !ordered.contains(d)).unwrap();
+                }
+                let cycle_start = path.iter().position(|item| *item == current).unwrap();
+                return Err(path.split_off(cycle_start));
+            },
+        };
+    }
+
+    Ok(ordered)
+}
+
+/// A template from a collection of templates in one file, whose one file is given inline.
 #[derive(Debug, Clone)]
-pub enum ConfigParseError {
-    YamlParseError(saphyr::ScanError),
-    ConfigMustBeAMapping,
-    NoOutputConfig,
-    OutputConfigMustBeAMapping,
-    NoOutputType,
-    InvalidOutputType,
+pub struct InlineTemplateConfig {
+    /// The name of the template, which is also the name of its file
+    name: String,
+
+    /// The contents of the template's file
+    body: String,
+
+    config: TemplateConfig,
+}
+
+impl InlineTemplateConfig {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn get_config(&self) -> &TemplateConfig {
+        &self.config
+    }
+
+    pub fn into_config(self) -> TemplateConfig {
+        self.config
+    }
+}
+
+/// Builds a [TemplateConfig] step by step, e.g.
+/// `TemplateConfig::builder().file("out.rs").include("*.rs").variable(variable).build()`.
+///
+/// Unlike the setters on [TemplateConfig], nothing is silently ignored: everything is collected in
+/// the same layout as a configuration file, and [TemplateConfigBuilder::build] checks it in the
+/// same way as [parse_config_from_yaml_string] does, e.g. that a File template has exactly one
+/// included glob. Errors have the key path the value would have in a configuration file.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateConfigBuilder {
+    /// The configuration so far, as it would be written in a configuration file
+    document: Map<String, Value>,
+    /// The first two different output types that were set, if more than one was
+    conflicting_output_types: Option<(String, String)>,
+}
+
+impl TemplateConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            document: Map::new(),
+            conflicting_output_types: None,
+        }
+    }
+
+    /// Makes the template render a single file, named `filename` by default.
+    pub fn file<S: Into<String>>(self, filename: S) -> Self {
+        self.output("file", CONFIG_KEY_OUTPUT_FILENAME, filename.into())
+    }
+
+    /// Makes the template render a directory tree, named `directory` by default.
+    pub fn directory<S: Into<String>>(self, directory: S) -> Self {
+        self.output("directory", CONFIG_KEY_OUTPUT_DIRECTORY, directory.into())
+    }
+
+    /// Makes the template render a snippet, which is inserted into `filename` as described by
+    /// `injection`.
+    pub fn inject<S: Into<String>>(self, filename: S, injection: &Injection) -> Self {
+        let mut builder = self.output("inject", CONFIG_KEY_OUTPUT_FILENAME, filename.into());
+        if let Some(Value::Object(output_map)) = builder.document.get_mut(CONFIG_KEY_OUTPUT_TOP_LEVEL) {
+            insert_injection(output_map, injection);
+        }
+        builder
+    }
+
+    /// Sets the template this one extends, relative to the directory containing this template.
+    pub fn extends<S: Into<String>>(mut self, parent_template: S) -> Self {
+        self.document.insert(CONFIG_KEY_EXTENDS.to_string(), Value::from(parent_template.into()));
+        self
+    }
+
+    /// Adds a glob matching files that this template will render. It takes priority over every glob
+    /// included before it, but the excluded globs apply after all of the included ones.
+    pub fn include<S: AsRef<str>>(self, glob: S) -> Self {
+        // A leading `!` would otherwise make it an exclusion
+        let glob = glob.as_ref();
+        let raw_glob = if glob.starts_with('!') { format!("\\{}", glob) } else { glob.to_string() };
+        self.push(CONFIG_KEY_INCLUDED_FILES, Value::from(raw_glob))
+    }
+
+    /// Adds a glob matching files that this template won't render. Like the `exclude` list of a
+    /// configuration file, it takes priority over every included glob and every glob excluded before it.
+    pub fn exclude<S: AsRef<str>>(self, glob: S) -> Self {
+        // A leading `!` would otherwise make it an inclusion
+        let glob = glob.as_ref();
+        let raw_glob = if glob.starts_with('!') { format!("\\{}", glob) } else { glob.to_string() };
+        self.push(CONFIG_KEY_EXCLUDED_FILES, Value::from(raw_glob))
+    }
+
+    /// Adds a glob matching files that are copied to the output as-is, instead of being rendered.
+    pub fn copy_without_render<S: Into<String>>(self, glob: S) -> Self {
+        self.push(CONFIG_KEY_COPY_WITHOUT_RENDER, Value::from(glob.into()))
+    }
+
+    /// Adds a condition for the files matching a glob, which are only rendered when it's true.
+    pub fn file_condition<S: Into<String>, C: Into<String>>(self, glob: S, condition: C) -> Self {
+        self.insert(CONFIG_KEY_FILE_CONDITIONS, glob.into(), Value::from(condition.into()))
+    }
+
+    /// Adds a rule rewriting the output path of the files matching `pattern`: a glob, or a regex
+    /// prefixed by `re:`.
+    pub fn rename<S: Into<String>, T: Into<String>>(self, pattern: S, target: T) -> Self {
+        self.insert(CONFIG_KEY_RENAME_RULES, pattern.into(), Value::from(target.into()))
+    }
+
+    /// Adds a glob matching files that are merged into an existing file at their output path.
+    pub fn merge<S: Into<String>>(self, glob: S, array_strategy: ArrayMergeStrategy) -> Self {
+        self.insert(CONFIG_KEY_MERGE, glob.into(), Value::from(array_strategy.get_name()))
+    }
+
+    /// Sets the prefix that stands in for a leading `.` in file and directory names; one of
+    /// [DOTFILE_PREFIXES].
+    pub fn dotfile_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.document.insert(CONFIG_KEY_DOTFILE_PREFIX.to_string(), Value::from(prefix.into()));
+        self
+    }
+
+    /// Adds another template to render into this template's output.
+    pub fn subtemplate(self, subtemplate: &Subtemplate) -> Self {
+        self.push(CONFIG_KEY_SUBTEMPLATES, subtemplate_to_value(subtemplate))
+    }
+
+    /// Adds a shell command to run in the output directory before any files are written.
+    pub fn pre_render_hook<S: Into<String>>(self, command: S) -> Self {
+        self.push_hook(CONFIG_KEY_HOOKS_PRE_RENDER, command.into())
+    }
+
+    /// Adds a shell command to run in the output directory after all the files are written.
+    pub fn post_render_hook<S: Into<String>>(self, command: S) -> Self {
+        self.push_hook(CONFIG_KEY_HOOKS_POST_RENDER, command.into())
+    }
+
+    /// Adds a variable, replacing any variable added before with the same name.
+    pub fn variable(self, variable: &TemplateVariable) -> Self {
+        self.insert(CONFIG_KEY_VARIABLES, variable.get_name().to_string(), variable_to_value(variable))
+    }
+
+    /// Checks the configuration and builds it, failing in the same cases as parsing it from a
+    /// configuration file would.
+    pub fn build(self) -> Result<TemplateConfig, ConfigParseError> {
+        if let Some((first_output_type, second_output_type)) = self.conflicting_output_types {
+            return Err(ConfigParseErrorKind::ConflictingOutputTypes(first_output_type, second_output_type).at(CONFIG_KEY_OUTPUT_TYPE));
+        }
+        parse_config_from_yaml_node(&value_to_owned_yaml(&Value::Object(self.document)))
+    }
+
+    /// Sets the output type, and the one output setting that goes with it. Setting a different output
+    /// type from before is recorded as a conflict, which [Self::build] reports.
+    fn output(mut self, output_type: &str, output_key: &str, output_value: String) -> Self {
+        if let Some(Value::String(previous_output_type)) = self.document.get(CONFIG_KEY_OUTPUT_TYPE)
+            && previous_output_type != output_type
+            && self.conflicting_output_types.is_none()
+        {
+            self.conflicting_output_types = Some((previous_output_type.clone(), output_type.to_string()));
+        }
+        let mut output_map = Map::new();
+        output_map.insert(output_key.to_string(), Value::from(output_value));
+        self.document.insert(CONFIG_KEY_OUTPUT_TYPE.to_string(), Value::from(output_type));
+        self.document.insert(CONFIG_KEY_OUTPUT_TOP_LEVEL.to_string(), Value::Object(output_map));
+        self
+    }
+
+    /// Adds a value to the end of the list at `key`.
+    fn push(mut self, key: &str, value: Value) -> Self {
+        if let Value::Array(items) = self.document.entry(key).or_insert_with(|| Value::Array(Vec::new())) {
+            items.push(value);
+        }
+        self
+    }
+
+    /// Adds an entry to the mapping at `key`.
+    fn insert(mut self, key: &str, entry_key: String, value: Value) -> Self {
+        if let Value::Object(entries) = self.document.entry(key).or_insert_with(|| Value::Object(Map::new())) {
+            entries.insert(entry_key, value);
+        }
+        self
+    }
+
+    /// Adds a command to the end of the list of hooks at `hook_key`.
+    fn push_hook(mut self, hook_key: &str, command: String) -> Self {
+        if let Value::Object(hooks_map) = self.document.entry(CONFIG_KEY_HOOKS).or_insert_with(|| Value::Object(Map::new()))
+            && let Value::Array(commands) = hooks_map.entry(hook_key).or_insert_with(|| Value::Array(Vec::new()))
+        {
+            commands.push(Value::from(command));
+        }
+        self
+    }
+}
+
+/// Serializes the configuration in the same layout as a configuration file; see
+/// [TemplateConfig::to_value].
+#[cfg(feature = "serde")]
+impl serde::Serialize for TemplateConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+/// Deserializes the configuration from the same layout as a configuration file, which is validated
+/// in the same way as when it's parsed.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TemplateConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        parse_config_from_yaml_node(&value_to_owned_yaml(&value)).map_err(serde::de::Error::custom)
+    }
+}
+
+/// One step along the path to a value in the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigKey {
+    /// A key in a mapping
+    Name(String),
+
+    /// A position in a sequence
+    Index(usize),
+}
+
+impl From<&str> for ConfigKey {
+    fn from(name: &str) -> Self {
+        ConfigKey::Name(name.to_string())
+    }
+}
+
+impl From<&String> for ConfigKey {
+    fn from(name: &String) -> Self {
+        ConfigKey::Name(name.clone())
+    }
+}
+
+impl From<String> for ConfigKey {
+    fn from(name: String) -> Self {
+        ConfigKey::Name(name)
+    }
+}
+
+impl From<usize> for ConfigKey {
+    fn from(index: usize) -> Self {
+        ConfigKey::Index(index)
+    }
+}
+
+/// What's wrong with a configuration.
+#[derive(Debug, Clone)]
+pub enum ConfigParseErrorKind {
+    YamlParseError(saphyr::ScanError),
+    TomlParseError(String),
+    JsonParseError(String),
+    NoConfig,
+    ConfigMustBeAMapping,
+    NoOutputConfig,
+    OutputConfigMustBeAMapping,
+    NoOutputType,
+    InvalidOutputType,
+    ConflictingOutputTypes(String, String),
     NoOutputFilename,
     InvalidOutputFilename,
     NoOutputDirectory,
@@ -172,190 +1307,1283 @@ pub enum ConfigParseError {
     TooManyIncludedFileGlobs,
     IncludedFileGlobMustBeString,
     IncludedFileGlobParseError(Option<String>, globset::ErrorKind),
+    InvalidExcludedFiles,
+    ExcludedFileGlobMustBeString,
+    InvalidCopyWithoutRender,
+    CopyWithoutRenderGlobMustBeString,
+    CopyWithoutRenderGlobParseError(Option<String>, globset::ErrorKind),
+    FileConditionsMustBeAMapping,
+    FileConditionGlobMustBeAString,
+    FileConditionMustBeAString,
+    FileConditionGlobParseError(Option<String>, globset::ErrorKind),
+    RenameRulesMustBeAMapping,
+    RenamePatternMustBeAString,
+    RenameTargetMustBeAString,
+    RenameGlobParseError(Option<String>, globset::ErrorKind),
+    RenameRegexParseError(String),
+    InvalidDotfilePrefix(String),
+    MergeRulesMustBeAMapping,
+    MergeGlobMustBeAString,
+    MergeGlobParseError(Option<String>, globset::ErrorKind),
+    InvalidMergeArrayStrategy(String),
+    HooksMustBeAMapping,
+    ExtendsMustBeAString,
+    NoInjectAnchor,
+    InjectAnchorMustBeAString,
+    InjectAnchorParseError(String),
+    InvalidInjectPosition,
+    NoInjectEndAnchor,
+    SubtemplatesMustBeASequence,
+    SubtemplateMustBeAMapping,
+    NoSubtemplateTemplate,
+    SubtemplateTemplateMustBeAString,
+    SubtemplateIntoMustBeAString,
+    SubtemplateVariablesMustBeAMapping,
+    SubtemplateVariableMustBeAScalar,
+    HookCommandsMustBeASequence,
+    HookCommandMustBeAString,
     VariablesMustBeAMapping,
     VariableNameMustBeAString,
     VariableDefaultMustBeAScalar,
+    VariablePromptMustBeAString,
+    VariableHelpMustBeAString,
+    VariableChoicesMustBeASequence,
+    VariableChoiceMustBeAScalar,
+    VariableMultipleMustBeABoolean,
+    VariableMultipleRequiresChoices,
+    VariableDefaultNotInChoices(String),
+    VariableDefaultCycle(Vec<String>),
+    VariableGroupCycle(Vec<Option<String>>),
+    VariableConditionMustBeAString,
+    VariableGroupMustBeAString,
+    VariableAdvancedMustBeABoolean,
+    VariableValidationMustBeAMapping,
+    VariableRequiredMustBeABoolean,
+    VariablePatternMustBeAString,
+    VariablePatternParseError(String),
+    VariableLengthMustBeAnInteger,
+    VariableBoundMustBeANumber,
+    InvalidVariableKind(String),
+    NoInlineTemplateName,
+    InlineTemplateNameMustBeAString,
+    DuplicateInlineTemplateName(String),
+    NoInlineTemplateBody,
+    InlineTemplateBodyMustBeAString,
+    InlineTemplateMustBeAFile,
+    InlineTemplateCannotExtend,
 }
 
-pub fn parse_config_from_yaml_string(yaml: &str) -> Result<TemplateConfig, ConfigParseError> {
+impl ConfigParseErrorKind {
+    /// Makes an error about the value at `key`. Use [ConfigParseError::within] to add the keys
+    /// leading to it.
+    fn at<K: Into<ConfigKey>>(self, key: K) -> ConfigParseError {
+        ConfigParseError {
+            kind: self,
+            key_path: vec![key.into()],
+            location: None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_glob_error = |f: &mut fmt::Formatter<'_>, glob: &Option<String>, glob_error: &globset::ErrorKind| match glob {
+            Some(glob) => write!(f, "invalid glob '{}': {}", glob, glob_error),
+            None => write!(f, "invalid glob: {}", glob_error),
+        };
+
+        match self {
+            ConfigParseErrorKind::YamlParseError(scan_error) => write!(f, "invalid YAML: {}", scan_error.info()),
+            ConfigParseErrorKind::TomlParseError(toml_error) => write!(f, "invalid TOML: {}", toml_error.trim_end()),
+            ConfigParseErrorKind::JsonParseError(json_error) => write!(f, "invalid JSON: {}", json_error),
+            ConfigParseErrorKind::NoConfig => write!(f, "the configuration is empty"),
+            ConfigParseErrorKind::ConfigMustBeAMapping => write!(f, "the configuration must be a mapping"),
+            ConfigParseErrorKind::NoOutputConfig => write!(f, "missing '{}'", CONFIG_KEY_OUTPUT_TOP_LEVEL),
+            ConfigParseErrorKind::OutputConfigMustBeAMapping => write!(f, "'{}' must be a mapping", CONFIG_KEY_OUTPUT_TOP_LEVEL),
+            ConfigParseErrorKind::NoOutputType => write!(f, "missing '{}'", CONFIG_KEY_OUTPUT_TYPE),
+            ConfigParseErrorKind::InvalidOutputType => write!(f, "the output type must be one of 'file', 'directory', or 'inject'"),
+            ConfigParseErrorKind::ConflictingOutputTypes(first_output_type, second_output_type) => write!(f, "the output type was set to both '{}' and '{}'", first_output_type, second_output_type),
+            ConfigParseErrorKind::NoOutputFilename => write!(f, "missing '{}', which file and inject templates need", CONFIG_KEY_OUTPUT_FILENAME),
+            ConfigParseErrorKind::InvalidOutputFilename => write!(f, "the output filename must be a string"),
+            ConfigParseErrorKind::NoOutputDirectory => write!(f, "missing '{}', which directory templates need", CONFIG_KEY_OUTPUT_DIRECTORY),
+            ConfigParseErrorKind::InvalidOutputDirectory => write!(f, "the output directory must be a string"),
+            ConfigParseErrorKind::NoIncludedFiles => write!(f, "missing '{}'", CONFIG_KEY_INCLUDED_FILES),
+            ConfigParseErrorKind::InvalidIncludedFiles => write!(f, "the included files must be a glob or a list of globs"),
+            ConfigParseErrorKind::TooManyIncludedFileGlobs => write!(f, "file and inject templates can only include a single glob"),
+            ConfigParseErrorKind::IncludedFileGlobMustBeString => write!(f, "included file globs must be strings"),
+            ConfigParseErrorKind::IncludedFileGlobParseError(glob, glob_error) => write_glob_error(f, glob, glob_error),
+            ConfigParseErrorKind::InvalidExcludedFiles => write!(f, "the excluded files must be a glob or a list of globs"),
+            ConfigParseErrorKind::ExcludedFileGlobMustBeString => write!(f, "excluded file globs must be strings"),
+            ConfigParseErrorKind::InvalidCopyWithoutRender => write!(f, "the files to copy without rendering must be a glob or a list of globs"),
+            ConfigParseErrorKind::CopyWithoutRenderGlobMustBeString => write!(f, "globs for files to copy without rendering must be strings"),
+            ConfigParseErrorKind::CopyWithoutRenderGlobParseError(glob, glob_error) => write_glob_error(f, glob, glob_error),
+            ConfigParseErrorKind::FileConditionsMustBeAMapping => write!(f, "file conditions must be a mapping of globs to conditions"),
+            ConfigParseErrorKind::FileConditionGlobMustBeAString => write!(f, "file condition globs must be strings"),
+            ConfigParseErrorKind::FileConditionMustBeAString => write!(f, "file conditions must be strings or booleans"),
+            ConfigParseErrorKind::FileConditionGlobParseError(glob, glob_error) => write_glob_error(f, glob, glob_error),
+            ConfigParseErrorKind::RenameRulesMustBeAMapping => write!(f, "rename rules must be a mapping of patterns to paths"),
+            ConfigParseErrorKind::RenamePatternMustBeAString => write!(f, "rename patterns must be strings"),
+            ConfigParseErrorKind::RenameTargetMustBeAString => write!(f, "rename targets must be strings"),
+            ConfigParseErrorKind::RenameGlobParseError(glob, glob_error) => write_glob_error(f, glob, glob_error),
+            ConfigParseErrorKind::RenameRegexParseError(regex_error) => write!(f, "invalid regex: {}", regex_error),
+            ConfigParseErrorKind::InvalidDotfilePrefix(prefix) => write!(f, "invalid dotfile prefix '{}', expected one of: {}", prefix, DOTFILE_PREFIXES.join(", ")),
+            ConfigParseErrorKind::MergeRulesMustBeAMapping => write!(f, "merge rules must be a mapping of globs to array strategies"),
+            ConfigParseErrorKind::MergeGlobMustBeAString => write!(f, "merge globs must be strings"),
+            ConfigParseErrorKind::MergeGlobParseError(glob, glob_error) => write_glob_error(f, glob, glob_error),
+            ConfigParseErrorKind::InvalidMergeArrayStrategy(strategy) => write!(f, "invalid array strategy '{}', expected one of: replace, append, union", strategy),
+            ConfigParseErrorKind::HooksMustBeAMapping => write!(f, "hooks must be a mapping"),
+            ConfigParseErrorKind::ExtendsMustBeAString => write!(f, "the template to extend must be a string"),
+            ConfigParseErrorKind::NoInjectAnchor => write!(f, "missing '{}' or '{}', which inject templates need", CONFIG_KEY_INJECT_ANCHOR, CONFIG_KEY_INJECT_MARKER),
+            ConfigParseErrorKind::InjectAnchorMustBeAString => write!(f, "give either a regex or a marker string, not both"),
+            ConfigParseErrorKind::InjectAnchorParseError(regex_error) => write!(f, "invalid regex: {}", regex_error),
+            ConfigParseErrorKind::InvalidInjectPosition => write!(f, "the position must be one of 'before', 'after', or 'replace_between'"),
+            ConfigParseErrorKind::NoInjectEndAnchor => write!(f, "missing '{}' or '{}', which 'replace_between' needs", CONFIG_KEY_INJECT_END_ANCHOR, CONFIG_KEY_INJECT_END_MARKER),
+            ConfigParseErrorKind::SubtemplatesMustBeASequence => write!(f, "subtemplates must be a list"),
+            ConfigParseErrorKind::SubtemplateMustBeAMapping => write!(f, "a subtemplate must be a template name or a mapping"),
+            ConfigParseErrorKind::NoSubtemplateTemplate => write!(f, "missing '{}'", CONFIG_KEY_SUBTEMPLATE_TEMPLATE),
+            ConfigParseErrorKind::SubtemplateTemplateMustBeAString => write!(f, "the subtemplate name must be a string"),
+            ConfigParseErrorKind::SubtemplateIntoMustBeAString => write!(f, "the subtemplate directory must be a string"),
+            ConfigParseErrorKind::SubtemplateVariablesMustBeAMapping => write!(f, "subtemplate variables must be a mapping"),
+            ConfigParseErrorKind::SubtemplateVariableMustBeAScalar => write!(f, "subtemplate variable values must be scalars"),
+            ConfigParseErrorKind::HookCommandsMustBeASequence => write!(f, "hooks must be a command or a list of commands"),
+            ConfigParseErrorKind::HookCommandMustBeAString => write!(f, "hook commands must be strings"),
+            ConfigParseErrorKind::VariablesMustBeAMapping => write!(f, "variables must be a mapping"),
+            ConfigParseErrorKind::VariableNameMustBeAString => write!(f, "variable names must be strings"),
+            ConfigParseErrorKind::VariableDefaultMustBeAScalar => write!(f, "the default value must be a scalar"),
+            ConfigParseErrorKind::VariablePromptMustBeAString => write!(f, "the prompt must be a string"),
+            ConfigParseErrorKind::VariableHelpMustBeAString => write!(f, "the help text must be a string"),
+            ConfigParseErrorKind::VariableChoicesMustBeASequence => write!(f, "the choices must be a non-empty list"),
+            ConfigParseErrorKind::VariableChoiceMustBeAScalar => write!(f, "choices must be scalars"),
+            ConfigParseErrorKind::VariableMultipleMustBeABoolean => write!(f, "'{}' must be a boolean", CONFIG_KEY_VARIABLE_MULTIPLE),
+            ConfigParseErrorKind::VariableMultipleRequiresChoices => write!(f, "'{}' needs '{}' to be given", CONFIG_KEY_VARIABLE_MULTIPLE, CONFIG_KEY_VARIABLE_CHOICES),
+            ConfigParseErrorKind::VariableDefaultNotInChoices(variable_name) => write!(f, "the default value of '{}' is not one of its choices", variable_name),
+            ConfigParseErrorKind::VariableDefaultCycle(variable_names) => write!(f, "computed defaults refer to each other in a loop: {}", variable_names.join(" -> ")),
+            ConfigParseErrorKind::VariableGroupCycle(group_names) => {
+                let group_names: Vec<&str> = group_names.iter().map(|g| g.as_deref().unwrap_or("(ungrouped)")).collect();
+                write!(f, "variable groups refer to each other in a loop: {}", group_names.join(" -> "))
+            },
+            ConfigParseErrorKind::VariableConditionMustBeAString => write!(f, "the condition must be a string or a boolean"),
+            ConfigParseErrorKind::VariableGroupMustBeAString => write!(f, "the group must be a string"),
+            ConfigParseErrorKind::VariableAdvancedMustBeABoolean => write!(f, "'{}' must be a boolean", CONFIG_KEY_VARIABLE_ADVANCED),
+            ConfigParseErrorKind::VariableValidationMustBeAMapping => write!(f, "validation rules must be a mapping"),
+            ConfigParseErrorKind::VariableRequiredMustBeABoolean => write!(f, "'{}' must be a boolean", CONFIG_KEY_VALIDATION_REQUIRED),
+            ConfigParseErrorKind::VariablePatternMustBeAString => write!(f, "the pattern must be a string"),
+            ConfigParseErrorKind::VariablePatternParseError(regex_error) => write!(f, "invalid regex: {}", regex_error),
+            ConfigParseErrorKind::VariableLengthMustBeAnInteger => write!(f, "lengths must be non-negative integers"),
+            ConfigParseErrorKind::VariableBoundMustBeANumber => write!(f, "bounds must be numbers"),
+            ConfigParseErrorKind::InvalidVariableKind(kind_name) => write!(f, "unknown kind '{}'", kind_name),
+            ConfigParseErrorKind::NoInlineTemplateName => write!(f, "missing '{}', which every template in a collection needs", CONFIG_KEY_INLINE_NAME),
+            ConfigParseErrorKind::InlineTemplateNameMustBeAString => write!(f, "the template name must be a string"),
+            ConfigParseErrorKind::DuplicateInlineTemplateName(name) => write!(f, "there is already a template named '{}'", name),
+            ConfigParseErrorKind::NoInlineTemplateBody => write!(f, "missing '{}', which every template in a collection needs", CONFIG_KEY_INLINE_BODY),
+            ConfigParseErrorKind::InlineTemplateBodyMustBeAString => write!(f, "the template body must be a string"),
+            ConfigParseErrorKind::InlineTemplateMustBeAFile => write!(f, "templates in a collection render a single file, so they must be file or inject templates"),
+            ConfigParseErrorKind::InlineTemplateCannotExtend => write!(f, "templates in a collection can't extend other templates"),
+        }
+    }
+}
+
+/// An error in a configuration, along with where it is.
+///
+/// Before 0.2.0 this was an enum of what's wrong; those variants are now [ConfigParseErrorKind],
+/// so code that matched on the error should match on [Self::get_kind] instead.
+#[derive(Debug, Clone)]
+pub struct ConfigParseError {
+    kind: ConfigParseErrorKind,
+
+    /// The keys leading to the offending value, from the top of the configuration; empty if it's
+    /// the configuration as a whole
+    key_path: Vec<ConfigKey>,
+
+    /// The line and column (both starting at 1) of the offending value, or of the closest enclosing
+    /// value that exists, if known
+    location: Option<(usize, usize)>,
+}
+
+impl ConfigParseError {
+    pub fn get_kind(&self) -> &ConfigParseErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ConfigParseErrorKind {
+        self.kind
+    }
+
+    pub fn get_key_path(&self) -> &[ConfigKey] {
+        &self.key_path
+    }
+
+    /// Returns the key path written out the way it would be looked up, e.g. `variables.name.choices[2]`.
+    pub fn get_key_path_string(&self) -> String {
+        let mut key_path = String::new();
+        for key in self.key_path.iter() {
+            match key {
+                ConfigKey::Name(name) if key_path.is_empty() => key_path.push_str(name),
+                ConfigKey::Name(name) => {
+                    key_path.push('.');
+                    key_path.push_str(name);
+                },
+                ConfigKey::Index(index) => key_path.push_str(&format!("[{}]", index)),
+            };
+        }
+        key_path
+    }
+
+    /// Returns the line and column (both starting at 1) in the configuration file that the error is
+    /// about, if known.
+    pub fn get_location(&self) -> Option<(usize, usize)> {
+        self.location
+    }
+
+    /// Adds `key` to the start of the key path, for an error about a value inside the value at `key`.
+    fn within<K: Into<ConfigKey>>(mut self, key: K) -> Self {
+        self.key_path.insert(0, key.into());
+        self
+    }
+
+    /// Sets the location of the error, if it isn't already known.
+    fn with_location(mut self, location: Option<(usize, usize)>) -> Self {
+        self.location = self.location.or(location);
+        self
+    }
+
+    /// Looks up the location of the key path in `yaml`, the configuration the error came from. If
+    /// the path leads to a key that doesn't exist, the location of the closest value that does is used.
+    fn locate_in_yaml(self, yaml: &str) -> Self {
+        self.locate_in_yaml_document(yaml, 0)
+    }
 
+    /// Looks up the location of the key path in document `doc_index` of `yaml`, as for
+    /// [ConfigParseError::locate_in_yaml].
+    fn locate_in_yaml_document(mut self, yaml: &str, doc_index: usize) -> Self {
+        if self.location.is_some() {
+            return self;
+        }
+
+        // Only scalars have their location recorded, so containers are found by their key, or
+        // failing that, the first scalar inside them
+        if let Ok(docs) = MarkedYamlOwned::load_from_str(yaml) && let Some(mut node) = docs.get(doc_index) {
+            let mut key_marker = None;
+            for key in self.key_path.iter() {
+                let child = match (&node.data, key) {
+                    (YamlDataOwned::Mapping(mapping), ConfigKey::Name(name)) => mapping.iter()
+                        .find(|(key_node, _)| marked_yaml_key(key_node).as_ref() == Some(name))
+                        .map(|(key_node, value_node)| (Some(key_node.span.start), value_node)),
+                    (YamlDataOwned::Sequence(seq), ConfigKey::Index(index)) => seq.get(*index).map(|item_node| (None, item_node)),
+                    _ => None,
+                };
+                match child {
+                    Some((child_key_marker, child)) => {
+                        key_marker = child_key_marker;
+                        node = child;
+                    },
+                    None => break,
+                };
+            }
+
+            let marker = Some(node.span.start).filter(|marker| marker.line() > 0)
+                .or(key_marker)
+                .or_else(|| get_first_marker(node));
+            self.location = marker.map(|marker| (marker.line(), marker.col() + 1));
+        }
+
+        self
+    }
+
+    /// Looks up the location of the key path in `toml_str`, as for [ConfigParseError::locate_in_yaml].
+    fn locate_in_toml(self, toml_str: &str) -> Self {
+        if self.location.is_some() {
+            return self;
+        }
+
+        let root = match toml::de::DeTable::parse(toml_str) {
+            Ok(root) => toml::de::DeValue::Table(root.into_inner()),
+            Err(_) => return self,
+        };
+
+        // Tables that are only implied by their dotted keys or headers have no span of their own,
+        // so they're found by their key instead
+        let get_entry_span = |(key, value): (&toml::Spanned<Cow<str>>, &toml::Spanned<toml::de::DeValue>)| {
+            if value.span().is_empty() { key.span() } else { value.span() }
+        };
+
+        let mut node = &root;
+        let mut span = None;
+        for key in self.key_path.iter() {
+            let child = match (node, key) {
+                (toml::de::DeValue::Table(table), ConfigKey::Name(name)) => table.get_key_value(name.as_str()).map(|entry| (get_entry_span(entry), entry.1)),
+                (toml::de::DeValue::Array(array), ConfigKey::Index(index)) => array.get(*index).map(|item| (item.span(), item)),
+                _ => None,
+            };
+            match child {
+                Some((child_span, child)) => {
+                    span = Some(child_span);
+                    node = child.get_ref();
+                },
+                None => break,
+            };
+        }
+
+        let location = span.map(|span| get_line_and_column(toml_str, span.start));
+        self.with_location(location)
+    }
+}
+
+impl From<ConfigParseErrorKind> for ConfigParseError {
+    /// Makes an error about the configuration as a whole.
+    fn from(kind: ConfigParseErrorKind) -> Self {
+        let location = match &kind {
+            ConfigParseErrorKind::YamlParseError(scan_error) => Some((scan_error.marker().line(), scan_error.marker().col() + 1)),
+            _ => None,
+        };
+
+        Self {
+            kind,
+            key_path: Vec::new(),
+            location,
+        }
+    }
+}
+
+impl fmt::Display for ConfigParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.key_path.is_empty(), self.location) {
+            (true, None) => write!(f, "{}", self.kind),
+            (true, Some((line, column))) => write!(f, "{} (line {}, column {})", self.kind, line, column),
+            (false, None) => write!(f, "{}: {}", self.get_key_path_string(), self.kind),
+            (false, Some((line, column))) => write!(f, "{}: {} (line {}, column {})", self.get_key_path_string(), self.kind, line, column),
+        }
+    }
+}
+
+impl std::error::Error for ConfigParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ConfigParseErrorKind::YamlParseError(scan_error) => Some(scan_error),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the name a mapping key is looked up by in a key path.
+fn yaml_key(key: &YamlOwned) -> ConfigKey {
+    match key {
+        YamlOwned::Value(ScalarOwned::String(name)) => ConfigKey::Name(name.clone()),
+        YamlOwned::Value(scalar) => ConfigKey::Name(scalar_to_value(scalar).to_string()),
+        _ => ConfigKey::Name(String::new()),
+    }
+}
+
+/// Returns the location of the first scalar in `node`, if there is one.
+fn get_first_marker(node: &MarkedYamlOwned) -> Option<saphyr::Marker> {
+    match &node.data {
+        YamlDataOwned::Mapping(mapping) => mapping.iter().next().and_then(|(key_node, _)| get_first_marker(key_node)),
+        YamlDataOwned::Sequence(seq) => seq.iter().find_map(get_first_marker),
+        YamlDataOwned::Tagged(_, tagged_node) => get_first_marker(tagged_node),
+        _ => Some(node.span.start).filter(|marker| marker.line() > 0),
+    }
+}
+
+/// Returns the name a mapping key is looked up by in a key path, as for [yaml_key].
+fn marked_yaml_key(key: &MarkedYamlOwned) -> Option<String> {
+    match &key.data {
+        YamlDataOwned::Value(ScalarOwned::String(name)) => Some(name.clone()),
+        YamlDataOwned::Value(scalar) => Some(scalar_to_value(scalar).to_string()),
+        _ => None,
+    }
+}
+
+/// Parses a template configuration from YAML. Errors say which key is wrong, and where it is.
+pub fn parse_config_from_yaml_string(yaml: &str) -> Result<TemplateConfig, ConfigParseError> {
     // Load the YAML
-    match YamlOwned::load_from_str(yaml) {
-        Err(error) => {
-            Err(ConfigParseError::YamlParseError(error))
+    let docs = match YamlOwned::load_from_str(yaml) {
+        Err(error) => return Err(ConfigParseErrorKind::YamlParseError(error).into()),
+        Ok(docs) => docs,
+    };
+
+    // Only the first document is the configuration
+    match docs.first() {
+        Some(config_doc) => parse_config_from_yaml_node(config_doc).map_err(|parse_error| parse_error.locate_in_yaml(yaml)),
+        None => Err(ConfigParseErrorKind::NoConfig.into()),
+    }
+}
+
+/// Parses a collection of templates from YAML, where each document is a File or Inject template
+/// configuration with a `name`, and the `body` of the template's one file. The file is named after
+/// the template, which is what `include` globs (and merge globs) see; `include` can be left out.
+///
+/// Errors are about the document that's wrong, and where it is in the whole collection.
+pub fn parse_inline_configs_from_yaml_string(yaml: &str) -> Result<Vec<InlineTemplateConfig>, ConfigParseError> {
+    let docs = match YamlOwned::load_from_str(yaml) {
+        Err(error) => return Err(ConfigParseErrorKind::YamlParseError(error).into()),
+        Ok(docs) => docs,
+    };
+
+    let mut inline_configs: Vec<InlineTemplateConfig> = Vec::with_capacity(docs.len());
+    for (doc_index, doc) in docs.iter().enumerate() {
+        let inline_config = parse_inline_config_from_yaml_node(doc).map_err(|parse_error| parse_error.locate_in_yaml_document(yaml, doc_index))?;
+        if inline_configs.iter().any(|c| c.name == inline_config.name) {
+            return Err(ConfigParseErrorKind::DuplicateInlineTemplateName(inline_config.name).at(CONFIG_KEY_INLINE_NAME).locate_in_yaml_document(yaml, doc_index));
+        }
+        inline_configs.push(inline_config);
+    }
+
+    Ok(inline_configs)
+}
+
+/// Parses a single template of a collection, as for [parse_inline_configs_from_yaml_string].
+fn parse_inline_config_from_yaml_node(doc: &YamlOwned) -> Result<InlineTemplateConfig, ConfigParseError> {
+    let mut mapping = match doc {
+        YamlOwned::Mapping(mapping) => mapping.clone(),
+        _ => return Err(ConfigParseErrorKind::ConfigMustBeAMapping.into()),
+    };
+
+    let name = match mapping.remove(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_INLINE_NAME.to_string()))) {
+        Some(YamlOwned::Value(ScalarOwned::String(name))) => name,
+        Some(_) => return Err(ConfigParseErrorKind::InlineTemplateNameMustBeAString.at(CONFIG_KEY_INLINE_NAME)),
+        None => return Err(ConfigParseErrorKind::NoInlineTemplateName.into()),
+    };
+    let body = match mapping.remove(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_INLINE_BODY.to_string()))) {
+        Some(YamlOwned::Value(ScalarOwned::String(body))) => body,
+        Some(_) => return Err(ConfigParseErrorKind::InlineTemplateBodyMustBeAString.at(CONFIG_KEY_INLINE_BODY)),
+        None => return Err(ConfigParseErrorKind::NoInlineTemplateBody.into()),
+    };
+    if mapping.contains_key(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_EXTENDS.to_string()))) {
+        return Err(ConfigParseErrorKind::InlineTemplateCannotExtend.at(CONFIG_KEY_EXTENDS));
+    }
+
+    // The one file is always included, unless the configuration says otherwise
+    let included_files_key = YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_INCLUDED_FILES.to_string()));
+    if !mapping.contains_key(&included_files_key) {
+        mapping.insert(included_files_key, YamlOwned::Value(ScalarOwned::String(name.clone())));
+    }
+
+    let config = parse_config_from_yaml_node(&YamlOwned::Mapping(mapping))?;
+    if config.get_output_type() == TemplateOutputType::Directory {
+        return Err(ConfigParseErrorKind::InlineTemplateMustBeAFile.at(CONFIG_KEY_OUTPUT_TYPE));
+    }
+
+    Ok(InlineTemplateConfig {
+        name,
+        body,
+        config,
+    })
+}
+
+/// Parses a template configuration from TOML, which is laid out the same way as in YAML.
+pub fn parse_config_from_toml_string(toml_str: &str) -> Result<TemplateConfig, ConfigParseError> {
+    let table = match toml::from_str::<toml::Table>(toml_str) {
+        Err(toml_error) => {
+            let location = toml_error.span().map(|span| get_line_and_column(toml_str, span.start));
+            return Err(ConfigParseError::from(ConfigParseErrorKind::TomlParseError(toml_error.message().to_string())).with_location(location));
         },
-        Ok(docs) => {
-            let config_doc = &docs[0];
-            match config_doc {
-                YamlOwned::Mapping(mapping) => {
-                    let mut config = TemplateConfig::new();
-                    // Parse the data
-                    // - Output type
-                    let output_type = match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_TYPE.to_string()))) {
-                        Some(owned_val) => {
-                            match owned_val {
-                                YamlOwned::Value(ScalarOwned::String(val)) => {
-                                    match val.as_str() {
-                                        "file" => TemplateOutputType::File,
-                                        "directory" => TemplateOutputType::Directory,
-                                        _ => return Err(ConfigParseError::InvalidOutputType),
-                                    }
-                                },
-                                _ => return Err(ConfigParseError::InvalidOutputType),
-                            }
+        Ok(table) => table,
+    };
+
+    parse_config_from_yaml_node(&toml_to_yaml(&toml::Value::Table(table))).map_err(|parse_error| parse_error.locate_in_toml(toml_str))
+}
+
+/// Parses a template configuration from JSON, which is laid out the same way as in YAML.
+pub fn parse_config_from_json_string(json: &str) -> Result<TemplateConfig, ConfigParseError> {
+    let value = match serde_json::from_str::<Value>(json) {
+        Err(json_error) => {
+            let message = json_error.to_string();
+            let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message).to_string();
+            return Err(ConfigParseError::from(ConfigParseErrorKind::JsonParseError(message)).with_location(Some((json_error.line(), json_error.column()))));
+        },
+        Ok(value) => value,
+    };
+
+    // JSON is (nearly always) also valid YAML, which is how the location of an error is found
+    parse_config_from_yaml_node(&value_to_owned_yaml(&value)).map_err(|parse_error| parse_error.locate_in_yaml(json))
+}
+
+/// Parses a template configuration from a loaded YAML document, or the equivalent converted from
+/// another format.
+fn parse_config_from_yaml_node(config_doc: &YamlOwned) -> Result<TemplateConfig, ConfigParseError> {
+    match config_doc {
+        YamlOwned::Mapping(mapping) => {
+            let mut config = TemplateConfig::new();
+            // Parse the data
+            // - Parent template -- Not required; a template that extends another can leave out
+            // its output configuration and included files to use the parent's
+            match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_EXTENDS.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(parent_template))) => config.set_extends(Some(parent_template.clone())),
+                Some(_) => return Err(ConfigParseErrorKind::ExtendsMustBeAString.at(CONFIG_KEY_EXTENDS)),
+                None => {},
+            };
+            config.inherits_output = config.get_extends().is_some()
+                && !mapping.contains_key(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_TYPE.to_string())))
+                && !mapping.contains_key(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_TOP_LEVEL.to_string())));
+
+            if !config.inherits_output {
+                // - Output type
+                let output_type = match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_TYPE.to_string()))) {
+                    Some(owned_val) => {
+                        match owned_val {
+                            YamlOwned::Value(ScalarOwned::String(val)) => {
+                                match val.as_str() {
+                                    "file" => TemplateOutputType::File,
+                                    "directory" => TemplateOutputType::Directory,
+                                    "inject" => TemplateOutputType::Inject,
+                                    _ => return Err(ConfigParseErrorKind::InvalidOutputType.at(CONFIG_KEY_OUTPUT_TYPE)),
+                                }
+                            },
+                            _ => return Err(ConfigParseErrorKind::InvalidOutputType.at(CONFIG_KEY_OUTPUT_TYPE)),
+                        }
+                    },
+                    None => return Err(ConfigParseErrorKind::NoOutputType.into()),
+                };
+
+                config.set_output_type(output_type);
+
+                // - Output filename/directory
+                let output_mapping = match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_TOP_LEVEL.to_string()))) {
+                    Some(owned_val) => {
+                        match owned_val {
+                            YamlOwned::Mapping(owned_mapping) => {
+                                owned_mapping
+                            },
+                            _ => return Err(ConfigParseErrorKind::OutputConfigMustBeAMapping.at(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
+                        }
+                    },
+                    None => return Err(ConfigParseErrorKind::NoOutputConfig.into()),
+                };
+
+                match output_type {
+                    TemplateOutputType::File | TemplateOutputType::Inject => {
+                        match output_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_FILENAME.to_string()))) {
+                            Some(owned_val) => {
+                                match owned_val {
+                                    YamlOwned::Value(ScalarOwned::String(val)) => {
+                                        config.set_output_filename(val.clone());
+                                    },
+                                    _ => return Err(ConfigParseErrorKind::InvalidOutputFilename.at(CONFIG_KEY_OUTPUT_FILENAME).within(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
+                                }
+                            },
+                            None => return Err(ConfigParseErrorKind::NoOutputFilename.at(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
+                        };
+                    },
+                    TemplateOutputType::Directory => {
+                        match output_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_DIRECTORY.to_string()))) {
+                            Some(owned_val) => {
+                                match owned_val {
+                                    YamlOwned::Value(ScalarOwned::String(val)) => {
+                                        config.set_output_directory(val.clone());
+                                    },
+                                    _ => return Err(ConfigParseErrorKind::InvalidOutputDirectory.at(CONFIG_KEY_OUTPUT_DIRECTORY).within(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
+                                }
+                            },
+                            None => return Err(ConfigParseErrorKind::NoOutputDirectory.at(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
+                        };
+                    },
+                };
+
+                // -- Where the snippet of an Inject template goes
+                if output_type == TemplateOutputType::Inject {
+                    let anchor = parse_inject_anchor(output_mapping, CONFIG_KEY_INJECT_ANCHOR, CONFIG_KEY_INJECT_MARKER)
+                        .map_err(|parse_error| parse_error.within(CONFIG_KEY_OUTPUT_TOP_LEVEL))?
+                        .ok_or_else(|| ConfigParseErrorKind::NoInjectAnchor.at(CONFIG_KEY_OUTPUT_TOP_LEVEL))?;
+                    let position = match output_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_INJECT_POSITION.to_string()))) {
+                        Some(YamlOwned::Value(ScalarOwned::String(position))) => match position.as_str() {
+                            "before" => InjectPosition::Before,
+                            "after" => InjectPosition::After,
+                            "replace_between" => InjectPosition::ReplaceBetween,
+                            _ => return Err(ConfigParseErrorKind::InvalidInjectPosition.at(CONFIG_KEY_INJECT_POSITION).within(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
                         },
-                        None => return Err(ConfigParseError::NoOutputType),
+                        Some(_) => return Err(ConfigParseErrorKind::InvalidInjectPosition.at(CONFIG_KEY_INJECT_POSITION).within(CONFIG_KEY_OUTPUT_TOP_LEVEL)),
+                        None => InjectPosition::After,
                     };
+                    let end_anchor = parse_inject_anchor(output_mapping, CONFIG_KEY_INJECT_END_ANCHOR, CONFIG_KEY_INJECT_END_MARKER)
+                        .map_err(|parse_error| parse_error.within(CONFIG_KEY_OUTPUT_TOP_LEVEL))?;
+                    if position == InjectPosition::ReplaceBetween && end_anchor.is_none() {
+                        return Err(ConfigParseErrorKind::NoInjectEndAnchor.at(CONFIG_KEY_OUTPUT_TOP_LEVEL));
+                    }
+
+                    let mut injection = Injection::new(anchor, position);
+                    injection.set_end_anchor(end_anchor);
+                    config.set_injection(injection);
+                }
+            }
 
-                    config.set_output_type(output_type);
-
-                    // - Output filename/directory
-                    let output_mapping = match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_TOP_LEVEL.to_string()))) {
-                        Some(owned_val) => {
-                            match owned_val {
-                                YamlOwned::Mapping(owned_mapping) => {
-                                    owned_mapping
-                                },
-                                _ => return Err(ConfigParseError::OutputConfigMustBeAMapping),
+            // - Included files -- These entries are globs to be used for matching, not direct filenames.
+            // A leading `!` turns an entry into an exclusion, as in a gitignore file
+            match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_INCLUDED_FILES.to_string()))) {
+                Some(owned_val) => {
+                    let included_globs = match owned_val {
+                        YamlOwned::Value(ScalarOwned::String(val)) => vec![val.as_str()],
+                        YamlOwned::Sequence(seq) => {
+                            let mut included_globs = Vec::with_capacity(seq.len());
+                            for (index, v) in seq.iter().enumerate() {
+                                match v {
+                                    YamlOwned::Value(ScalarOwned::String(val)) => included_globs.push(val.as_str()),
+                                    _ => return Err(ConfigParseErrorKind::IncludedFileGlobMustBeString.at(index).within(CONFIG_KEY_INCLUDED_FILES)),
+                                };
                             }
+                            included_globs
                         },
-                        None => return Err(ConfigParseError::NoOutputConfig),
+                        _ => return Err(ConfigParseErrorKind::InvalidIncludedFiles.at(CONFIG_KEY_INCLUDED_FILES)),
                     };
 
-                    match output_type {
-                        TemplateOutputType::File => {
-                            match output_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_FILENAME.to_string()))) {
-                                Some(owned_val) => {
-                                    match owned_val {
-                                        YamlOwned::Value(ScalarOwned::String(val)) => {
-                                            config.set_output_filename(val.clone());
-                                        },
-                                        _ => return Err(ConfigParseError::InvalidOutputFilename),
-                                    }
-                                },
-                                None => return Err(ConfigParseError::NoOutputFilename),
+                    // If the output type is a File, then there should only be a single
+                    // filename glob provided
+                    if matches!(config.get_output_type(), TemplateOutputType::File | TemplateOutputType::Inject) && !config.inherits_output && included_globs.iter().filter(|g| !g.starts_with('!')).count() > 1 {
+                        return Err(ConfigParseErrorKind::TooManyIncludedFileGlobs.at(CONFIG_KEY_INCLUDED_FILES));
+                    }
+
+                    for (index, raw_glob) in included_globs.into_iter().enumerate() {
+                        let (glob, included) = parse_file_glob(raw_glob).map_err(|parse_error| parse_error.within(index).within(CONFIG_KEY_INCLUDED_FILES))?;
+                        config.add_listed_file_pattern(glob, included, false);
+                    }
+                },
+                None if config.get_extends().is_some() => {},
+                None => return Err(ConfigParseErrorKind::NoIncludedFiles.into()),
+            };
+
+            // - Excluded files -- Not required; these apply after the included files, and a
+            // leading `!` turns an entry back into an inclusion
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_EXCLUDED_FILES.to_string()))) {
+                let excluded_globs = match owned_val {
+                    YamlOwned::Value(ScalarOwned::String(val)) => vec![val.as_str()],
+                    YamlOwned::Sequence(seq) => {
+                        let mut excluded_globs = Vec::with_capacity(seq.len());
+                        for (index, v) in seq.iter().enumerate() {
+                            match v {
+                                YamlOwned::Value(ScalarOwned::String(val)) => excluded_globs.push(val.as_str()),
+                                _ => return Err(ConfigParseErrorKind::ExcludedFileGlobMustBeString.at(index).within(CONFIG_KEY_EXCLUDED_FILES)),
                             };
-                        },
-                        TemplateOutputType::Directory => {
-                            match output_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_OUTPUT_DIRECTORY.to_string()))) {
-                                Some(owned_val) => {
-                                    match owned_val {
-                                        YamlOwned::Value(ScalarOwned::String(val)) => {
-                                            config.set_output_directory(val.clone());
-                                        },
-                                        _ => return Err(ConfigParseError::InvalidOutputDirectory),
-                                    }
-                                },
-                                None => return Err(ConfigParseError::NoOutputDirectory),
+                        }
+                        excluded_globs
+                    },
+                    _ => return Err(ConfigParseErrorKind::InvalidExcludedFiles.at(CONFIG_KEY_EXCLUDED_FILES)),
+                };
+
+                for (index, raw_glob) in excluded_globs.into_iter().enumerate() {
+                    let (glob, excluded) = parse_file_glob(raw_glob).map_err(|parse_error| parse_error.within(index).within(CONFIG_KEY_EXCLUDED_FILES))?;
+                    config.add_listed_file_pattern(glob, !excluded, true);
+                }
+            }
+
+            // - Files to copy without rendering -- Not required; these are globs, like the included files
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_COPY_WITHOUT_RENDER.to_string()))) {
+                let verbatim_globs = match owned_val {
+                    YamlOwned::Value(ScalarOwned::String(val)) => vec![val.as_str()],
+                    YamlOwned::Sequence(seq) => {
+                        let mut verbatim_globs = Vec::with_capacity(seq.len());
+                        for (index, v) in seq.iter().enumerate() {
+                            match v {
+                                YamlOwned::Value(ScalarOwned::String(val)) => verbatim_globs.push(val.as_str()),
+                                _ => return Err(ConfigParseErrorKind::CopyWithoutRenderGlobMustBeString.at(index).within(CONFIG_KEY_COPY_WITHOUT_RENDER)),
                             };
+                        }
+                        verbatim_globs
+                    },
+                    _ => return Err(ConfigParseErrorKind::InvalidCopyWithoutRender.at(CONFIG_KEY_COPY_WITHOUT_RENDER)),
+                };
+
+                for (index, raw_glob) in verbatim_globs.into_iter().enumerate() {
+                    match Glob::new(raw_glob) {
+                        Ok(glob) => config.add_copy_without_render_pattern(glob),
+                        Err(glob_err) => {
+                            let originating_glob = glob_err.glob().map(|s| s.to_string());
+                            return Err(ConfigParseErrorKind::CopyWithoutRenderGlobParseError(originating_glob, glob_err.kind().clone()).at(index).within(CONFIG_KEY_COPY_WITHOUT_RENDER));
                         },
                     };
+                }
+            }
 
-                    // - Included files -- These entries are globs to be used for matching, not direct filenames
-                    match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_INCLUDED_FILES.to_string()))) {
-                        Some(owned_val) => {
-                            let mut file_globs = GlobSet::builder();
-                            match owned_val {
-                                YamlOwned::Value(ScalarOwned::String(val)) => {
-                                    match Glob::new(val.as_str()) {
-                                        Ok(glob) => file_globs.add(glob),
+            // - File conditions -- Not required; these map globs to the condition the matching
+            // files are rendered under
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_FILE_CONDITIONS.to_string()))) {
+                match owned_val {
+                    YamlOwned::Mapping(conditions_mapping) => {
+                        for (raw_glob, raw_condition) in conditions_mapping.iter() {
+                            let glob = match raw_glob {
+                                YamlOwned::Value(ScalarOwned::String(val)) => match Glob::new(val.as_str()) {
+                                    Ok(glob) => glob,
+                                    Err(glob_err) => {
+                                        let originating_glob = glob_err.glob().map(|s| s.to_string());
+                                        return Err(ConfigParseErrorKind::FileConditionGlobParseError(originating_glob, glob_err.kind().clone()).at(yaml_key(raw_glob)).within(CONFIG_KEY_FILE_CONDITIONS));
+                                    },
+                                },
+                                _ => return Err(ConfigParseErrorKind::FileConditionGlobMustBeAString.at(yaml_key(raw_glob)).within(CONFIG_KEY_FILE_CONDITIONS)),
+                            };
+                            match raw_condition {
+                                YamlOwned::Value(ScalarOwned::String(condition)) => config.add_file_condition(glob, condition.clone()),
+                                YamlOwned::Value(ScalarOwned::Boolean(condition)) => config.add_file_condition(glob, condition.to_string()),
+                                _ => return Err(ConfigParseErrorKind::FileConditionMustBeAString.at(yaml_key(raw_glob)).within(CONFIG_KEY_FILE_CONDITIONS)),
+                            };
+                        }
+                    },
+                    _ => return Err(ConfigParseErrorKind::FileConditionsMustBeAMapping.at(CONFIG_KEY_FILE_CONDITIONS)),
+                };
+            }
+
+            // - Rename rules -- Not required; these map globs (or regexes, with a prefix) to
+            // the path matching files are written to
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_RENAME_RULES.to_string()))) {
+                match owned_val {
+                    YamlOwned::Mapping(rename_mapping) => {
+                        for (raw_pattern, raw_target) in rename_mapping.iter() {
+                            let pattern = match raw_pattern {
+                                YamlOwned::Value(ScalarOwned::String(val)) => match val.strip_prefix(RENAME_REGEX_PREFIX) {
+                                    Some(raw_regex) => match Regex::new(raw_regex) {
+                                        Ok(regex) => RenamePattern::Regex(regex),
+                                        Err(regex_error) => return Err(ConfigParseErrorKind::RenameRegexParseError(regex_error.to_string()).at(yaml_key(raw_pattern)).within(CONFIG_KEY_RENAME_RULES)),
+                                    },
+                                    None => match Glob::new(val.as_str()) {
+                                        Ok(glob) => RenamePattern::Glob(glob.compile_matcher()),
                                         Err(glob_err) => {
-                                            let originating_glob = glob_err.glob().and_then(|s| Some(s.to_string()));
-                                            return Err(ConfigParseError::IncludedFileGlobParseError(originating_glob, glob_err.kind().clone()));
+                                            let originating_glob = glob_err.glob().map(|s| s.to_string());
+                                            return Err(ConfigParseErrorKind::RenameGlobParseError(originating_glob, glob_err.kind().clone()).at(yaml_key(raw_pattern)).within(CONFIG_KEY_RENAME_RULES));
                                         },
-                                    };
+                                    },
                                 },
-                                YamlOwned::Sequence(seq) => {
-                                    // If the output type is a File, then there should only be a
-                                    // single filename glob provided
-                                    if output_type == TemplateOutputType::File {
-                                        if seq.len() > 1 {
-                                            return Err(ConfigParseError::TooManyIncludedFileGlobs);
-                                        }
-                                    }
+                                _ => return Err(ConfigParseErrorKind::RenamePatternMustBeAString.at(yaml_key(raw_pattern)).within(CONFIG_KEY_RENAME_RULES)),
+                            };
+                            match raw_target {
+                                YamlOwned::Value(ScalarOwned::String(target)) => config.add_rename_rule(pattern, target.clone()),
+                                _ => return Err(ConfigParseErrorKind::RenameTargetMustBeAString.at(yaml_key(raw_pattern)).within(CONFIG_KEY_RENAME_RULES)),
+                            };
+                        }
+                    },
+                    _ => return Err(ConfigParseErrorKind::RenameRulesMustBeAMapping.at(CONFIG_KEY_RENAME_RULES)),
+                };
+            }
 
-                                    for v in seq {
-                                        match v {
-                                            YamlOwned::Value(ScalarOwned::String(val)) => {
-                                                match Glob::new(val.as_str()) {
-                                                    Ok(glob) => file_globs.add(glob),
-                                                    Err(glob_err) => {
-                                                        let originating_glob = glob_err.glob().and_then(|s| Some(s.to_string()));
-                                                        return Err(ConfigParseError::IncludedFileGlobParseError(originating_glob, glob_err.kind().clone()));
-                                                    },
-                                                };
-                                            },
-                                            _ => return Err(ConfigParseError::IncludedFileGlobMustBeString),
-                                        };
-                                    }
+            // - Merge rules -- Not required; these map globs to how arrays are combined when
+            // the matching files are merged into existing files
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_MERGE.to_string()))) {
+                match owned_val {
+                    YamlOwned::Mapping(merge_mapping) => {
+                        for (raw_glob, raw_strategy) in merge_mapping.iter() {
+                            let glob = match raw_glob {
+                                YamlOwned::Value(ScalarOwned::String(val)) => match Glob::new(val.as_str()) {
+                                    Ok(glob) => glob,
+                                    Err(glob_err) => {
+                                        let originating_glob = glob_err.glob().map(|s| s.to_string());
+                                        return Err(ConfigParseErrorKind::MergeGlobParseError(originating_glob, glob_err.kind().clone()).at(yaml_key(raw_glob)).within(CONFIG_KEY_MERGE));
+                                    },
                                 },
-                                _ => return Err(ConfigParseError::InvalidIncludedFiles),
+                                _ => return Err(ConfigParseErrorKind::MergeGlobMustBeAString.at(yaml_key(raw_glob)).within(CONFIG_KEY_MERGE)),
                             };
-                            match file_globs.build() {
-                                Ok(globset) => config.update_included_file_patterns(globset),
-                                Err(glob_err) => {
-                                    let originating_glob = glob_err.glob().and_then(|s| Some(s.to_string()));
-                                    return Err(ConfigParseError::IncludedFileGlobParseError(originating_glob, glob_err.kind().clone()));
+                            match raw_strategy {
+                                YamlOwned::Value(ScalarOwned::String(strategy_name)) => match ArrayMergeStrategy::from_name(strategy_name) {
+                                    Some(array_strategy) => config.add_merge_pattern(glob, array_strategy),
+                                    None => return Err(ConfigParseErrorKind::InvalidMergeArrayStrategy(strategy_name.clone()).at(yaml_key(raw_glob)).within(CONFIG_KEY_MERGE)),
                                 },
+                                _ => return Err(ConfigParseErrorKind::InvalidMergeArrayStrategy(String::new()).at(yaml_key(raw_glob)).within(CONFIG_KEY_MERGE)),
                             };
-                        },
-                        None => return Err(ConfigParseError::NoIncludedFiles),
-                    };
-                    
-
-                    // - Variables
-                    match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLES.to_string()))) {
-                        Some(owned_val) => {
-                            match owned_val {
-                                YamlOwned::Mapping(variables_mapping) => {
-                                    for (variable_name, variable_default_value) in variables_mapping.iter() {
-                                        match variable_name {
-                                            YamlOwned::Value(ScalarOwned::String(string_var_name)) => {
-                                                match variable_default_value {
-                                                    YamlOwned::Value(scalar_value) => {
-                                                        let var_name_key = string_var_name.to_string();
-                                                        match scalar_value {
-                                                            ScalarOwned::Null => config.add_variable(var_name_key, "".to_string()),
-                                                            ScalarOwned::Boolean(bool_default_value) => config.add_variable(var_name_key, bool_default_value.to_string()),
-                                                            ScalarOwned::Integer(int_default_value) => config.add_variable(var_name_key, int_default_value.to_string()),
-                                                            ScalarOwned::FloatingPoint(fp_default_value) => config.add_variable(var_name_key, fp_default_value.to_string()),
-                                                            ScalarOwned::String(string_default_value) => config.add_variable(var_name_key, string_default_value.to_string()),
-                                                        };
-                                                    },
-                                                    _ => return Err(ConfigParseError::VariableDefaultMustBeAScalar),
-                                                };
-                                            },
-                                            _ => return Err(ConfigParseError::VariableNameMustBeAString),
+                        }
+                    },
+                    _ => return Err(ConfigParseErrorKind::MergeRulesMustBeAMapping.at(CONFIG_KEY_MERGE)),
+                };
+            }
+
+            // - Dotfile prefix -- Not required; one of the supported prefixes
+            match mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_DOTFILE_PREFIX.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(prefix))) if DOTFILE_PREFIXES.contains(&prefix.as_str()) => config.set_dotfile_prefix(Some(prefix.clone())),
+                Some(YamlOwned::Value(ScalarOwned::String(prefix))) => return Err(ConfigParseErrorKind::InvalidDotfilePrefix(prefix.clone()).at(CONFIG_KEY_DOTFILE_PREFIX)),
+                Some(_) => return Err(ConfigParseErrorKind::InvalidDotfilePrefix(String::new()).at(CONFIG_KEY_DOTFILE_PREFIX)),
+                None => {},
+            };
+
+            // - Subtemplates -- Not required; each is a template name, or a mapping with the
+            // template name, where to render it, and values for its variables
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_SUBTEMPLATES.to_string()))) {
+                match owned_val {
+                    YamlOwned::Sequence(seq) => {
+                        for (index, v) in seq.iter().enumerate() {
+                            config.add_subtemplate(parse_subtemplate(v).map_err(|parse_error| parse_error.within(index).within(CONFIG_KEY_SUBTEMPLATES))?);
+                        }
+                    },
+                    _ => return Err(ConfigParseErrorKind::SubtemplatesMustBeASequence.at(CONFIG_KEY_SUBTEMPLATES)),
+                };
+            }
+
+            // - Hooks -- Not required; each kind of hook is a command or a list of commands
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_HOOKS.to_string()))) {
+                match owned_val {
+                    YamlOwned::Mapping(hooks_mapping) => {
+                        for hook_key in [CONFIG_KEY_HOOKS_PRE_RENDER, CONFIG_KEY_HOOKS_POST_RENDER] {
+                            let commands = match hooks_mapping.get(&YamlOwned::Value(ScalarOwned::String(hook_key.to_string()))) {
+                                Some(YamlOwned::Value(ScalarOwned::String(command))) => vec![command.clone()],
+                                Some(YamlOwned::Sequence(seq)) => {
+                                    let mut commands = Vec::with_capacity(seq.len());
+                                    for (index, v) in seq.iter().enumerate() {
+                                        match v {
+                                            YamlOwned::Value(ScalarOwned::String(command)) => commands.push(command.clone()),
+                                            _ => return Err(ConfigParseErrorKind::HookCommandMustBeAString.at(index).within(hook_key).within(CONFIG_KEY_HOOKS)),
                                         };
                                     }
+                                    commands
                                 },
-                                _ => return Err(ConfigParseError::VariablesMustBeAMapping),
+                                Some(_) => return Err(ConfigParseErrorKind::HookCommandsMustBeASequence.at(hook_key).within(CONFIG_KEY_HOOKS)),
+                                None => Vec::new(),
                             };
-                        },
-                        None => {},  // Do nothing, variables are not a required field
-                    };
 
-                    // All done, return the config
-                    Ok(config)
+                            for command in commands {
+                                if hook_key == CONFIG_KEY_HOOKS_PRE_RENDER {
+                                    config.add_pre_render_hook(command);
+                                } else {
+                                    config.add_post_render_hook(command);
+                                }
+                            }
+                        }
+                    },
+                    _ => return Err(ConfigParseErrorKind::HooksMustBeAMapping.at(CONFIG_KEY_HOOKS)),
+                };
+            }
+
+            // - Variables -- These are not a required field, so there's nothing to do if they're absent
+            if let Some(owned_val) = mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLES.to_string()))) {
+                match owned_val {
+                    YamlOwned::Mapping(variables_mapping) => {
+                        for (variable_name, variable_definition) in variables_mapping.iter() {
+                            match variable_name {
+                                YamlOwned::Value(ScalarOwned::String(string_var_name)) => {
+                                    config.add_template_variable(parse_variable(string_var_name, variable_definition).map_err(|parse_error| parse_error.within(string_var_name).within(CONFIG_KEY_VARIABLES))?);
+                                },
+                                _ => return Err(ConfigParseErrorKind::VariableNameMustBeAString.at(yaml_key(variable_name)).within(CONFIG_KEY_VARIABLES)),
+                            };
+                        }
+                    },
+                    _ => return Err(ConfigParseErrorKind::VariablesMustBeAMapping.at(CONFIG_KEY_VARIABLES)),
+                };
+            }
+
+            // - Computed defaults can reference other variables, but not in a loop
+            if let Some(cycle) = config.find_variable_cycle() {
+                let first_variable = cycle[0].clone();
+                return Err(ConfigParseErrorKind::VariableDefaultCycle(cycle).at(first_variable).within(CONFIG_KEY_VARIABLES));
+            }
+
+            // - Each group is prompted for in one go, so groups can't reference each other in a loop
+            if let Some(cycle) = config.find_variable_group_cycle() {
+                return Err(ConfigParseErrorKind::VariableGroupCycle(cycle).at(CONFIG_KEY_VARIABLES));
+            }
+
+            // All done, return the config
+            Ok(config)
+        },
+        _ => Err(ConfigParseErrorKind::ConfigMustBeAMapping.into()),
+    }
+}
+
+/// Parses a file glob from an include or exclude list, returning the glob and whether it was written
+/// as-is (true) or negated with a leading `!` (false). A leading `\!` matches a literal `!`.
+fn parse_file_glob(raw_glob: &str) -> Result<(Glob, bool), ConfigParseError> {
+    let (glob_str, as_is) = match raw_glob.strip_prefix('!') {
+        Some(negated_glob) => (negated_glob, false),
+        None => (raw_glob.strip_prefix('\\').filter(|g| g.starts_with('!')).unwrap_or(raw_glob), true),
+    };
+
+    match Glob::new(glob_str) {
+        Ok(glob) => Ok((glob, as_is)),
+        Err(glob_err) => {
+            let originating_glob = glob_err.glob().map(|s| s.to_string());
+            Err(ConfigParseErrorKind::IncludedFileGlobParseError(originating_glob, glob_err.kind().clone()).into())
+        },
+    }
+}
+
+/// Adds the anchors and position of an Inject template to its output configuration.
+fn insert_injection(output_map: &mut Map<String, Value>, injection: &Injection) {
+    output_map.insert(CONFIG_KEY_INJECT_ANCHOR.to_string(), Value::from(injection.anchor.as_str()));
+    if let Some(end_anchor) = &injection.end_anchor {
+        output_map.insert(CONFIG_KEY_INJECT_END_ANCHOR.to_string(), Value::from(end_anchor.as_str()));
+    }
+    let position = match injection.position {
+        InjectPosition::Before => "before",
+        InjectPosition::After => "after",
+        InjectPosition::ReplaceBetween => "replace_between",
+    };
+    output_map.insert(CONFIG_KEY_INJECT_POSITION.to_string(), Value::from(position));
+}
+
+/// Returns a subtemplate as it's written in the subtemplates list.
+fn subtemplate_to_value(subtemplate: &Subtemplate) -> Value {
+    let mut subtemplate_map = Map::new();
+    subtemplate_map.insert(CONFIG_KEY_SUBTEMPLATE_TEMPLATE.to_string(), Value::from(subtemplate.template.as_str()));
+    subtemplate_map.insert(CONFIG_KEY_SUBTEMPLATE_INTO.to_string(), Value::from(subtemplate.into.as_str()));
+    if !subtemplate.variables.is_empty() {
+        subtemplate_map.insert(CONFIG_KEY_SUBTEMPLATE_VARIABLES.to_string(), Value::Object(subtemplate.variables.iter().cloned().collect()));
+    }
+    Value::Object(subtemplate_map)
+}
+
+/// Returns the definition of a variable as it's written in the configuration: just the default
+/// value if that's all there is to it, and a mapping otherwise.
+fn variable_to_value(variable: &TemplateVariable) -> Value {
+    let validation = variable.get_validation();
+    let has_prompt = variable.get_prompt() != variable.get_name();
+    let is_short_form = !has_prompt
+        && variable.get_help().is_none()
+        && variable.get_choices().is_none()
+        && !variable.is_multiple()
+        && *validation == VariableValidation::default()
+        && variable.get_condition().is_none()
+        && variable.get_group().is_none()
+        && !variable.is_advanced()
+        && !matches!(variable.get_default(), Value::Array(_) | Value::Object(_));
+    if is_short_form {
+        return variable.get_default().clone();
+    }
+
+    let mut definition_map = Map::new();
+    definition_map.insert(CONFIG_KEY_VARIABLE_DEFAULT.to_string(), variable.get_default().clone());
+    if has_prompt {
+        definition_map.insert(CONFIG_KEY_VARIABLE_PROMPT.to_string(), Value::from(variable.get_prompt()));
+    }
+    if let Some(help) = variable.get_help() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_HELP.to_string(), Value::from(help));
+    }
+    if let Some(choices) = variable.get_choices() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_CHOICES.to_string(), Value::Array(choices.to_vec()));
+    }
+    if variable.is_multiple() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_MULTIPLE.to_string(), Value::from(true));
+    }
+    if let Some(condition) = variable.get_condition() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_CONDITION.to_string(), Value::from(condition));
+    }
+    if let Some(group) = variable.get_group() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_GROUP.to_string(), Value::from(group));
+    }
+    if variable.is_advanced() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_ADVANCED.to_string(), Value::from(true));
+    }
+
+    // - Validation rules -- Only the ones that are set
+    let mut validation_map = Map::new();
+    if validation.required {
+        validation_map.insert(CONFIG_KEY_VALIDATION_REQUIRED.to_string(), Value::from(true));
+    }
+    if let Some(pattern) = &validation.pattern {
+        validation_map.insert(CONFIG_KEY_VALIDATION_PATTERN.to_string(), Value::from(pattern.as_str()));
+    }
+    for (length_key, length) in [(CONFIG_KEY_VALIDATION_MIN_LENGTH, validation.min_length), (CONFIG_KEY_VALIDATION_MAX_LENGTH, validation.max_length)] {
+        if let Some(length) = length {
+            validation_map.insert(length_key.to_string(), Value::from(length));
+        }
+    }
+    for (bound_key, bound) in [(CONFIG_KEY_VALIDATION_MIN, validation.min), (CONFIG_KEY_VALIDATION_MAX, validation.max)] {
+        if let Some(bound) = bound {
+            validation_map.insert(bound_key.to_string(), Value::from(bound));
+        }
+    }
+    if let Some(kind) = validation.kind {
+        validation_map.insert(CONFIG_KEY_VALIDATION_KIND.to_string(), Value::from(kind.to_string()));
+    }
+    if !validation_map.is_empty() {
+        definition_map.insert(CONFIG_KEY_VARIABLE_VALIDATION.to_string(), Value::Object(validation_map));
+    }
+
+    Value::Object(definition_map)
+}
+
+/// Parses the definition of a single variable. This is either just the default value (the short
+/// form), or a mapping with the default value alongside prompt and help text, and any choices.
+fn parse_variable(variable_name: &str, definition: &YamlOwned) -> Result<TemplateVariable, ConfigParseError> {
+    match definition {
+        YamlOwned::Value(scalar_value) => Ok(TemplateVariable::new(variable_name.to_string(), scalar_to_value(scalar_value))),
+        YamlOwned::Mapping(definition_mapping) => {
+            // - Choices
+            let choices = match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_CHOICES.to_string()))) {
+                Some(YamlOwned::Sequence(choices_seq)) if !choices_seq.is_empty() => {
+                    let mut choices = Vec::with_capacity(choices_seq.len());
+                    for (index, choice) in choices_seq.iter().enumerate() {
+                        match choice {
+                            YamlOwned::Value(scalar_value) => choices.push(scalar_to_value(scalar_value)),
+                            _ => return Err(ConfigParseErrorKind::VariableChoiceMustBeAScalar.at(index).within(CONFIG_KEY_VARIABLE_CHOICES)),
+                        };
+                    }
+                    Some(choices)
+                },
+                Some(_) => return Err(ConfigParseErrorKind::VariableChoicesMustBeASequence.at(CONFIG_KEY_VARIABLE_CHOICES)),
+                None => None,
+            };
+
+            // - Multiple selection
+            let multiple = match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_MULTIPLE.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::Boolean(multiple))) => *multiple,
+                Some(_) => return Err(ConfigParseErrorKind::VariableMultipleMustBeABoolean.at(CONFIG_KEY_VARIABLE_MULTIPLE)),
+                None => false,
+            };
+
+            if multiple && choices.is_none() {
+                return Err(ConfigParseErrorKind::VariableMultipleRequiresChoices.at(CONFIG_KEY_VARIABLE_MULTIPLE));
+            }
+
+            // - Default value -- Optional. Without one, a single-select variable defaults to its first
+            // choice, a multi-select variable defaults to selecting nothing, and anything else
+            // defaults to null.
+            let default = match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_DEFAULT.to_string()))) {
+                Some(YamlOwned::Value(scalar_value)) if multiple => Value::Array(vec![scalar_to_value(scalar_value)]),
+                Some(YamlOwned::Value(scalar_value)) => scalar_to_value(scalar_value),
+                Some(YamlOwned::Sequence(default_seq)) if multiple => {
+                    let mut defaults = Vec::with_capacity(default_seq.len());
+                    for (index, default_value) in default_seq.iter().enumerate() {
+                        match default_value {
+                            YamlOwned::Value(scalar_value) => defaults.push(scalar_to_value(scalar_value)),
+                            _ => return Err(ConfigParseErrorKind::VariableDefaultMustBeAScalar.at(index).within(CONFIG_KEY_VARIABLE_DEFAULT)),
+                        };
+                    }
+                    Value::Array(defaults)
                 },
-                _ => Err(ConfigParseError::ConfigMustBeAMapping),
+                Some(_) => return Err(ConfigParseErrorKind::VariableDefaultMustBeAScalar.at(CONFIG_KEY_VARIABLE_DEFAULT)),
+                None => {
+                    match &choices {
+                        Some(_) if multiple => Value::Array(vec![]),
+                        Some(choices) => choices[0].clone(),
+                        None => Value::Null,
+                    }
+                },
+            };
+            let mut variable = TemplateVariable::new(variable_name.to_string(), default);
+            variable.set_choices(choices);
+            variable.set_multiple(multiple);
+
+            // A computed default is only known once it's rendered, so its value is checked along with
+            // the rest of the context instead
+            if !variable.has_computed_default() && variable.validate_choices(variable.get_default()).is_err() {
+                return Err(ConfigParseErrorKind::VariableDefaultNotInChoices(variable_name.to_string()).at(CONFIG_KEY_VARIABLE_DEFAULT));
+            }
+
+            // - Prompt
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_PROMPT.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(prompt))) => variable.set_prompt(Some(prompt.clone())),
+                Some(_) => return Err(ConfigParseErrorKind::VariablePromptMustBeAString.at(CONFIG_KEY_VARIABLE_PROMPT)),
+                None => {},
+            };
+
+            // - Help text
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_HELP.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(help))) => variable.set_help(Some(help.clone())),
+                Some(_) => return Err(ConfigParseErrorKind::VariableHelpMustBeAString.at(CONFIG_KEY_VARIABLE_HELP)),
+                None => {},
+            };
+
+            // - Condition -- Booleans are allowed too, since they're valid (if not very useful) expressions
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_CONDITION.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(condition))) => variable.set_condition(Some(condition.clone())),
+                Some(YamlOwned::Value(ScalarOwned::Boolean(condition))) => variable.set_condition(Some(condition.to_string())),
+                Some(_) => return Err(ConfigParseErrorKind::VariableConditionMustBeAString.at(CONFIG_KEY_VARIABLE_CONDITION)),
+                None => {},
+            };
+
+            // - Group
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_GROUP.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(group))) => variable.set_group(Some(group.clone())),
+                Some(_) => return Err(ConfigParseErrorKind::VariableGroupMustBeAString.at(CONFIG_KEY_VARIABLE_GROUP)),
+                None => {},
+            };
+
+            // - Advanced
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_ADVANCED.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::Boolean(advanced))) => variable.set_advanced(*advanced),
+                Some(_) => return Err(ConfigParseErrorKind::VariableAdvancedMustBeABoolean.at(CONFIG_KEY_VARIABLE_ADVANCED)),
+                None => {},
+            };
+
+            // - Validation rules
+            if let Some(validation) = definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VARIABLE_VALIDATION.to_string()))) {
+                variable.set_validation(parse_variable_validation(validation).map_err(|parse_error| parse_error.within(CONFIG_KEY_VARIABLE_VALIDATION))?);
             }
+
+            Ok(variable)
+        },
+        _ => Err(ConfigParseErrorKind::VariableDefaultMustBeAScalar.into()),
+    }
+}
+
+/// Parses an anchor for an Inject template from the output configuration: either a regex under
+/// `regex_key`, or literal text under `marker_key`. Returns None if neither is present.
+fn parse_inject_anchor(output_mapping: &MappingOwned, regex_key: &str, marker_key: &str) -> Result<Option<Regex>, ConfigParseError> {
+    let regex_value = output_mapping.get(&YamlOwned::Value(ScalarOwned::String(regex_key.to_string())));
+    let anchor_key = if regex_value.is_some() { regex_key } else { marker_key };
+    let raw_regex = match (regex_value, output_mapping.get(&YamlOwned::Value(ScalarOwned::String(marker_key.to_string())))) {
+        (Some(YamlOwned::Value(ScalarOwned::String(anchor))), None) => anchor.clone(),
+        (None, Some(YamlOwned::Value(ScalarOwned::String(marker)))) => regex::escape(marker),
+        (None, None) => return Ok(None),
+        _ => return Err(ConfigParseErrorKind::InjectAnchorMustBeAString.at(anchor_key)),
+    };
+
+    match Regex::new(&raw_regex) {
+        Ok(anchor) => Ok(Some(anchor)),
+        Err(regex_error) => Err(ConfigParseErrorKind::InjectAnchorParseError(regex_error.to_string()).at(anchor_key)),
+    }
+}
+
+/// Parses a single entry of the subtemplates list.
+fn parse_subtemplate(definition: &YamlOwned) -> Result<Subtemplate, ConfigParseError> {
+    match definition {
+        YamlOwned::Value(ScalarOwned::String(template)) => Ok(Subtemplate::new(template.clone())),
+        YamlOwned::Mapping(definition_mapping) => {
+            // - Template
+            let mut subtemplate = match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_SUBTEMPLATE_TEMPLATE.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(template))) => Subtemplate::new(template.clone()),
+                Some(_) => return Err(ConfigParseErrorKind::SubtemplateTemplateMustBeAString.at(CONFIG_KEY_SUBTEMPLATE_TEMPLATE)),
+                None => return Err(ConfigParseErrorKind::NoSubtemplateTemplate.into()),
+            };
+
+            // - Output directory
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_SUBTEMPLATE_INTO.to_string()))) {
+                Some(YamlOwned::Value(ScalarOwned::String(into))) => subtemplate.set_into(into.clone()),
+                Some(_) => return Err(ConfigParseErrorKind::SubtemplateIntoMustBeAString.at(CONFIG_KEY_SUBTEMPLATE_INTO)),
+                None => {},
+            };
+
+            // - Variables
+            match definition_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_SUBTEMPLATE_VARIABLES.to_string()))) {
+                Some(YamlOwned::Mapping(variables_mapping)) => {
+                    for (variable_name, value) in variables_mapping.iter() {
+                        match (variable_name, value) {
+                            (YamlOwned::Value(ScalarOwned::String(variable_name)), YamlOwned::Value(scalar)) => subtemplate.add_variable(variable_name.clone(), scalar_to_value(scalar)),
+                            _ => return Err(ConfigParseErrorKind::SubtemplateVariableMustBeAScalar.at(yaml_key(variable_name)).within(CONFIG_KEY_SUBTEMPLATE_VARIABLES)),
+                        };
+                    }
+                },
+                Some(_) => return Err(ConfigParseErrorKind::SubtemplateVariablesMustBeAMapping.at(CONFIG_KEY_SUBTEMPLATE_VARIABLES)),
+                None => {},
+            };
+
+            Ok(subtemplate)
         },
+        _ => Err(ConfigParseErrorKind::SubtemplateMustBeAMapping.into()),
+    }
+}
+
+/// Parses the validation rules for a variable.
+fn parse_variable_validation(validation: &YamlOwned) -> Result<VariableValidation, ConfigParseError> {
+    let validation_mapping = match validation {
+        YamlOwned::Mapping(validation_mapping) => validation_mapping,
+        _ => return Err(ConfigParseErrorKind::VariableValidationMustBeAMapping.into()),
+    };
+
+    let get_length = |key: &str| -> Result<Option<usize>, ConfigParseError> {
+        match validation_mapping.get(&YamlOwned::Value(ScalarOwned::String(key.to_string()))) {
+            Some(YamlOwned::Value(ScalarOwned::Integer(length))) if *length >= 0 => Ok(Some(*length as usize)),
+            Some(_) => Err(ConfigParseErrorKind::VariableLengthMustBeAnInteger.at(key)),
+            None => Ok(None),
+        }
+    };
+
+    let get_bound = |key: &str| -> Result<Option<f64>, ConfigParseError> {
+        match validation_mapping.get(&YamlOwned::Value(ScalarOwned::String(key.to_string()))) {
+            Some(YamlOwned::Value(ScalarOwned::Integer(bound))) => Ok(Some(*bound as f64)),
+            Some(YamlOwned::Value(ScalarOwned::FloatingPoint(bound))) => Ok(Some(bound.into_inner())),
+            Some(_) => Err(ConfigParseErrorKind::VariableBoundMustBeANumber.at(key)),
+            None => Ok(None),
+        }
+    };
+
+    let required = match validation_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VALIDATION_REQUIRED.to_string()))) {
+        Some(YamlOwned::Value(ScalarOwned::Boolean(required))) => *required,
+        Some(_) => return Err(ConfigParseErrorKind::VariableRequiredMustBeABoolean.at(CONFIG_KEY_VALIDATION_REQUIRED)),
+        None => false,
+    };
+
+    let pattern = match validation_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VALIDATION_PATTERN.to_string()))) {
+        Some(YamlOwned::Value(ScalarOwned::String(pattern))) => {
+            match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(regex_error) => return Err(ConfigParseErrorKind::VariablePatternParseError(regex_error.to_string()).at(CONFIG_KEY_VALIDATION_PATTERN)),
+            }
+        },
+        Some(_) => return Err(ConfigParseErrorKind::VariablePatternMustBeAString.at(CONFIG_KEY_VALIDATION_PATTERN)),
+        None => None,
+    };
+
+    let kind = match validation_mapping.get(&YamlOwned::Value(ScalarOwned::String(CONFIG_KEY_VALIDATION_KIND.to_string()))) {
+        Some(YamlOwned::Value(ScalarOwned::String(kind_name))) => {
+            match ValueKind::from_name(kind_name) {
+                Some(kind) => Some(kind),
+                None => return Err(ConfigParseErrorKind::InvalidVariableKind(kind_name.clone()).at(CONFIG_KEY_VALIDATION_KIND)),
+            }
+        },
+        Some(_) => return Err(ConfigParseErrorKind::InvalidVariableKind(String::new()).at(CONFIG_KEY_VALIDATION_KIND)),
+        None => None,
+    };
+
+    Ok(VariableValidation {
+        required,
+        pattern,
+        min_length: get_length(CONFIG_KEY_VALIDATION_MIN_LENGTH)?,
+        max_length: get_length(CONFIG_KEY_VALIDATION_MAX_LENGTH)?,
+        min: get_bound(CONFIG_KEY_VALIDATION_MIN)?,
+        max: get_bound(CONFIG_KEY_VALIDATION_MAX)?,
+        kind,
+    })
+}
+
+/// The formats a configuration file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Works out the format of a configuration file from its extension, which is YAML unless it's
+    /// `.toml` or `.json`.
+    pub fn from_path<P: AsRef<Path>>(path: &P) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum ConfigParseFromFileError {
     FileReadError(std::io::Error),
-    ParseError(ConfigParseError),
+    /// The file isn't a valid configuration: (file, error)
+    ParseError(PathBuf, Box<ConfigParseError>),
+}
+
+impl fmt::Display for ConfigParseFromFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigParseFromFileError::FileReadError(read_error) => write!(f, "could not read the configuration: {}", read_error),
+            ConfigParseFromFileError::ParseError(path, parse_error) => write!(f, "{}: {}", path.display(), parse_error),
+        }
+    }
+}
+
+impl std::error::Error for ConfigParseFromFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigParseFromFileError::FileReadError(read_error) => Some(read_error),
+            ConfigParseFromFileError::ParseError(_, parse_error) => Some(parse_error.as_ref()),
+        }
+    }
+}
+
+/// Parses a collection of templates from a YAML file; see [parse_inline_configs_from_yaml_string].
+pub fn parse_inline_configs_from_file<P: AsRef<Path>>(path: &P) -> Result<Vec<InlineTemplateConfig>, ConfigParseFromFileError> {
+    match read_to_string(path) {
+        Err(read_error) => Err(ConfigParseFromFileError::FileReadError(read_error)),
+        Ok(collection_str) => parse_inline_configs_from_yaml_string(&collection_str)
+            .map_err(|parse_error| ConfigParseFromFileError::ParseError(path.as_ref().to_path_buf(), Box::new(parse_error))),
+    }
 }
 
+/// Parses a template configuration from a file, in the format given by its extension (YAML if it
+/// has no recognised extension).
 pub fn parse_config_from_file<P: AsRef<Path>>(path: &P) -> Result<TemplateConfig, ConfigParseFromFileError> {
     match read_to_string(path) {
         Err(read_error) => Err(ConfigParseFromFileError::FileReadError(read_error)),
         Ok(config_str) => {
-            match parse_config_from_yaml_string(&config_str) {
-                Err(parse_error) => Err(ConfigParseFromFileError::ParseError(parse_error)),
+            let parse_result = match ConfigFormat::from_path(path) {
+                ConfigFormat::Yaml => parse_config_from_yaml_string(&config_str),
+                ConfigFormat::Toml => parse_config_from_toml_string(&config_str),
+                ConfigFormat::Json => parse_config_from_json_string(&config_str),
+            };
+            match parse_result {
+                Err(parse_error) => Err(ConfigParseFromFileError::ParseError(path.as_ref().to_path_buf(), Box::new(parse_error))),
                 Ok(config) => Ok(config),
             }
         }
@@ -403,6 +2631,992 @@ mod test {
         assert_eq!(config.get_output_directory(), Some("test-dir"), "output directory not parsed correctly");
     }
 
+    #[test]
+    pub fn parse_config_keeps_variable_types() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          a_string: abcd
+          a_number_string: "1234"
+          a_bool: false
+          an_int: 12345
+          a_float: 1.5
+          a_null: ~
+        "#).unwrap();
+
+        assert_eq!(config.get_variable_type("a_string"), Some(VariableType::String));
+        assert_eq!(config.get_variable_type("a_number_string"), Some(VariableType::String));
+        assert_eq!(config.get_variable_type("a_bool"), Some(VariableType::Boolean));
+        assert_eq!(config.get_variable_type("an_int"), Some(VariableType::Integer));
+        assert_eq!(config.get_variable_type("a_float"), Some(VariableType::Float));
+        assert_eq!(config.get_variable_type("a_null"), Some(VariableType::String));
+        assert_eq!(config.get_variable_type("missing"), None);
+
+        let context = config.get_render_context(None).unwrap();
+        assert_eq!(context.get("a_bool"), Some(&Value::from(false)));
+        assert_eq!(context.get("an_int"), Some(&Value::from(12345)));
+        assert_eq!(tera::Tera::one_off("{% if a_bool %}yes{% else %}no{% endif %} {{ an_int + 1 }}", &context, false).unwrap(), "no 12346");
+    }
+
+    #[test]
+    pub fn parse_config_works_for_extended_variables() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          short: abcd
+          extended:
+            prompt: What is the extended value?
+            help: This explains the extended value.
+            default: 12
+          no_default:
+            prompt: Anything?
+        "#).unwrap();
+
+        let short = config.get_variable("short").unwrap();
+        assert_eq!(short.get_default(), &Value::from("abcd"));
+        assert_eq!(short.get_prompt(), "short");
+        assert_eq!(short.get_help(), None);
+
+        let extended = config.get_variable("extended").unwrap();
+        assert_eq!(extended.get_default(), &Value::from(12));
+        assert_eq!(extended.get_type(), VariableType::Integer);
+        assert_eq!(extended.get_prompt(), "What is the extended value?");
+        assert_eq!(extended.get_help(), Some("This explains the extended value."));
+
+        let no_default = config.get_variable("no_default").unwrap();
+        assert_eq!(no_default.get_default(), &Value::Null);
+        assert_eq!(no_default.get_prompt(), "Anything?");
+    }
+
+    #[test]
+    pub fn parse_config_works_for_choice_variables() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          license:
+            choices: [MIT, Apache-2.0]
+          edition:
+            choices: [2018, 2021, 2024]
+            default: 2024
+          ci:
+            choices: [github, gitlab, jenkins]
+            multiple: true
+            default: [github, jenkins]
+        "#).unwrap();
+
+        assert_eq!(config.get_variable("license").unwrap().get_default(), &Value::from("MIT"));
+        assert_eq!(config.get_variable("edition").unwrap().get_default(), &Value::from(2024));
+        assert!(config.get_variable("ci").unwrap().is_multiple());
+        assert_eq!(config.get_variable("ci").unwrap().get_default(), &Value::from(vec!["github", "jenkins"]));
+
+        let mut context = tera::Context::new();
+        context.insert("license", "Apache-2.0");
+        context.insert("ci", &vec!["gitlab"]);
+        assert!(config.validate_context(&context).is_ok());
+
+        context.insert("edition", &2015);
+        assert_eq!(config.validate_context(&context), Err(("edition".to_string(), VariableValueError::NotAChoice("2015".to_string()))));
+    }
+
+    #[test]
+    pub fn parse_config_rejects_invalid_choice_variables() {
+        let default_not_in_choices = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          license:
+            choices: [MIT, Apache-2.0]
+            default: BSD
+        "#);
+        assert!(matches!(default_not_in_choices.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::VariableDefaultNotInChoices(_))));
+
+        // Computed defaults are checked once they're rendered
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          server: false
+          database:
+            choices: [sqlite, postgres]
+            default: "{% if server %}postgres{% else %}sqlite{% endif %}"
+          cache:
+            choices: [none, redis]
+            default: "{{ database }}"
+        "#).unwrap();
+        let context = config.get_render_context(None).unwrap();
+        assert_eq!(context.get("database"), Some(&Value::from("sqlite")));
+        assert_eq!(config.validate_context(&context), Err(("cache".to_string(), VariableValueError::NotAChoice("sqlite".to_string()))));
+
+        let multiple_without_choices = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          license:
+            multiple: true
+        "#);
+        assert!(matches!(multiple_without_choices.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::VariableMultipleRequiresChoices)));
+    }
+
+    #[test]
+    pub fn parse_config_works_for_variable_validation() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          crate_name:
+            validate:
+              required: true
+              pattern: "^[a-z][a-z0-9_-]*$"
+              max_length: 64
+          port:
+            default: 8080
+            validate:
+              min: 1
+              max: 65535
+          author_email:
+            default: someone@example.com
+            validate:
+              kind: email
+        "#).unwrap();
+
+        let crate_name_validation = config.get_variable("crate_name").unwrap().get_validation();
+        assert!(crate_name_validation.required);
+        assert_eq!(crate_name_validation.pattern.as_ref().map(|p| p.as_str()), Some("^[a-z][a-z0-9_-]*$"));
+        assert_eq!(crate_name_validation.max_length, Some(64));
+        assert_eq!(config.get_variable("port").unwrap().get_validation().max, Some(65535.0));
+        assert_eq!(config.get_variable("author_email").unwrap().get_validation().kind, Some(ValueKind::Email));
+
+        // Values that are never provided still have to pass validation
+        let context = config.get_render_context(None).unwrap();
+        assert_eq!(config.validate_context(&context), Err(("crate_name".to_string(), VariableValueError::Required)));
+
+        let mut context = tera::Context::new();
+        context.insert("crate_name", "my crate");
+        assert!(matches!(config.validate_context(&context), Err((_, VariableValueError::PatternMismatch(_, _)))));
+
+        let invalid_kind = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          crate_name:
+            validate:
+              kind: phone_number
+        "#);
+        assert!(matches!(invalid_kind.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::InvalidVariableKind(_))));
+    }
+
+    #[test]
+    pub fn computed_defaults_resolve_in_dependency_order() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          a_module: "{{ crate_name }}_module"
+          crate_name: "{{ project_name | slugify }}"
+          project_name: My Project
+        "#).unwrap();
+
+        let ordered_names: Vec<&str> = config.get_variables().iter().map(|v| v.get_name()).collect();
+        assert_eq!(ordered_names, vec!["project_name", "crate_name", "a_module"]);
+
+        let declared_names: Vec<String> = config.get_variable_items().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(declared_names, vec!["a_module", "crate_name", "project_name"]);
+
+        let context = config.get_render_context(None).unwrap();
+        assert_eq!(context.get("crate_name"), Some(&Value::from("my-project")));
+        assert_eq!(context.get("a_module"), Some(&Value::from("my-project_module")));
+
+        let mut overrides = tera::Context::new();
+        overrides.insert("project_name", "Other Thing");
+        let context = config.get_render_context(Some(&overrides)).unwrap();
+        assert_eq!(context.get("a_module"), Some(&Value::from("other-thing_module")));
+    }
+
+    #[test]
+    pub fn computed_default_cycles_are_rejected() {
+        let cycle = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          first: "{{ second }}"
+          second: "{{ third }}"
+          third: "{{ first }}"
+          unrelated: "{{ first }}"
+        "#);
+        assert!(matches!(cycle.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::VariableDefaultCycle(names)) if names == vec!["first", "second", "third"]));
+
+        // Filter names and keywords aren't references, even when a variable has the same name
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          upper: "{{ name | upper }}"
+          not: "{% if not upper %}empty{% endif %}"
+          name: demo
+        "#).unwrap();
+        let context = config.get_render_context(None).unwrap();
+        assert_eq!(context.get("upper"), Some(&Value::from("DEMO")));
+        assert_eq!(context.get("not"), Some(&Value::from("")));
+    }
+
+    #[test]
+    pub fn conditional_variables_take_their_default_when_skipped() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          ci_provider:
+            choices: [github, gitlab]
+            when: use_ci
+          ci_file:
+            default: ".{{ ci_provider }}-ci.yml"
+            when: "{{ use_ci }}"
+          use_ci: true
+        "#).unwrap();
+
+        let ordered_names: Vec<&str> = config.get_variables().iter().map(|v| v.get_name()).collect();
+        assert_eq!(ordered_names, vec!["use_ci", "ci_provider", "ci_file"]);
+
+        let mut overrides = tera::Context::new();
+        overrides.insert("ci_provider", "gitlab");
+        let context = config.get_render_context(Some(&overrides)).unwrap();
+        assert_eq!(context.get("ci_file"), Some(&Value::from(".gitlab-ci.yml")));
+
+        // Skipped variables ignore overrides, and aren't validated
+        overrides.insert("use_ci", &false);
+        overrides.insert("ci_provider", "travis");
+        let context = config.get_render_context(Some(&overrides)).unwrap();
+        assert_eq!(context.get("ci_provider"), Some(&Value::from("github")));
+        assert!(config.validate_context(&overrides).is_ok());
+    }
+
+    #[test]
+    pub fn variables_keep_declaration_order_within_groups() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          project_name: abcd
+          use_ci:
+            default: true
+            group: CI
+          license: MIT
+          ci_provider:
+            default: github
+            group: CI
+            when: use_ci
+          ci_cache:
+            default: true
+            group: CI
+            advanced: true
+          zebra: z
+          alpha: a
+        "#).unwrap();
+
+        let ordered_names: Vec<&str> = config.get_variables().iter().map(|v| v.get_name()).collect();
+        assert_eq!(ordered_names, vec!["project_name", "license", "zebra", "alpha", "use_ci", "ci_provider", "ci_cache"]);
+
+        assert_eq!(config.get_variable("use_ci").unwrap().get_group(), Some("CI"));
+        assert!(config.get_variable("ci_cache").unwrap().is_advanced());
+        assert!(!config.get_variable("ci_provider").unwrap().is_advanced());
+
+        // A group that an earlier group depends on moves ahead of it as a whole
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          project_name: abcd
+          ci_provider:
+            default: github
+            group: CI
+          ci_image:
+            default: "{{ docker_registry }}/ci"
+            group: CI
+          docker_registry:
+            default: ghcr.io
+            group: Docker
+          docker_tag:
+            default: latest
+            group: Docker
+        "#).unwrap();
+
+        let ordered_groups: Vec<Option<&str>> = config.get_variables().iter().map(|v| v.get_group()).collect();
+        assert_eq!(ordered_groups, vec![None, Some("Docker"), Some("Docker"), Some("CI"), Some("CI")]);
+        for (index, group) in ordered_groups.iter().enumerate() {
+            assert!(!ordered_groups[index + 1..].iter().skip_while(|g| *g == group).any(|g| g == group));
+        }
+
+        let group_cycle = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: template.rs
+        variables:
+          ci_image:
+            default: "{{ docker_registry }}/ci"
+            group: CI
+          ci_provider:
+            default: github
+            group: CI
+          docker_registry:
+            default: "{{ ci_provider }}.example.com"
+            group: Docker
+        "#);
+        assert!(matches!(group_cycle.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::VariableGroupCycle(groups)) if groups == vec![Some("CI".to_string()), Some("Docker".to_string())]));
+    }
+
+    #[test]
+    pub fn exclude_patterns_apply_in_order() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include:
+          - "*"
+          - "!*.swp"
+          - "!docs/*.md"
+          - "docs/index.md"
+        exclude:
+          - "target/**"
+          - build
+          - "!build/keep.txt"
+          - "cache/**"
+          - "!cache/*.json"
+        "#).unwrap();
+
+        assert!(config.should_include_file(&"src/main.rs"));
+        assert!(!config.should_include_file(&"src/.main.rs.swp"));
+        assert!(!config.should_include_file(&"docs/guide.md"));
+        assert!(config.should_include_file(&"docs/index.md"));
+        assert!(!config.should_include_file(&"target/debug/app"));
+        assert!(!config.should_include_file(&"cache/data.bin"));
+        assert!(config.should_include_file(&"cache/data.json"));
+
+        // Nothing inside an excluded directory can be included again
+        assert!(!config.should_include_file(&"build/keep.txt"));
+
+        assert!(config.should_traverse_directory(&"src"));
+        assert!(!config.should_traverse_directory(&"target"));
+        assert!(!config.should_traverse_directory(&"target/debug"));
+        assert!(!config.should_traverse_directory(&"build"));
+        assert!(config.should_traverse_directory(&"cache"));
+    }
+
+    #[test]
+    pub fn file_templates_allow_negated_include_globs() {
+        let config = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: ["*.rs", "!*.bak.rs"]
+        "#).unwrap();
+        assert!(config.should_include_file(&"template.rs"));
+        assert!(!config.should_include_file(&"template.bak.rs"));
+
+        let too_many = parse_config_from_yaml_string(r#"
+        type: file
+        output:
+          filename: test.rs
+        include: ["*.rs", "*.txt"]
+        "#);
+        assert!(matches!(too_many.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::TooManyIncludedFileGlobs)));
+    }
+
+    #[test]
+    pub fn copy_without_render_patterns_are_parsed() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        copy_without_render:
+          - ".github/workflows/*.yml"
+          - "*.png"
+        "#).unwrap();
+
+        assert!(config.should_copy_without_render(&".github/workflows/ci.yml"));
+        assert!(config.should_copy_without_render(&"assets/logo.png"));
+        assert!(!config.should_copy_without_render(&"README.md"));
+
+        let invalid = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        copy_without_render: [1]
+        "#);
+        assert!(matches!(invalid.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::CopyWithoutRenderGlobMustBeString)));
+    }
+
+    #[test]
+    pub fn file_conditions_apply_to_matching_files() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        files:
+          Dockerfile: "{{ use_docker }}"
+          ".github/workflows/*.yml": use_ci and ci_provider == "github"
+          benches: false
+        variables:
+          use_docker: false
+          use_ci: true
+          ci_provider: github
+        "#).unwrap();
+        let context = config.get_render_context(None).unwrap();
+
+        assert!(!config.is_file_enabled(&"Dockerfile", &context).unwrap());
+        assert!(config.is_file_enabled(&".github/workflows/ci.yml", &context).unwrap());
+        assert!(!config.is_file_enabled(&"benches/bench.rs", &context).unwrap());
+        assert!(config.is_file_enabled(&"src/main.rs", &context).unwrap());
+
+        let invalid = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        files:
+          - Dockerfile
+        "#);
+        assert!(matches!(invalid.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::FileConditionsMustBeAMapping)));
+    }
+
+    #[test]
+    pub fn output_paths_are_renamed() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        dotfiles: dot_
+        rename:
+          _gitignore: .gitignore
+          "src/bin": "src/{{ crate_name }}"
+          're:^(.*)\.tmpl$': "$1"
+        "#).unwrap();
+
+        assert_eq!(config.get_output_path(&"_gitignore"), PathBuf::from(".gitignore"));
+        assert_eq!(config.get_output_path(&"src/bin/main.rs"), PathBuf::from("src/{{ crate_name }}/main.rs"));
+        assert_eq!(config.get_output_path(&"docs/index.html.tmpl"), PathBuf::from("docs/index.html"));
+        assert_eq!(config.get_output_path(&"dot_github/workflows/dot_keep"), PathBuf::from(".github/workflows/.keep"));
+        assert_eq!(config.get_output_path(&"src/main.rs"), PathBuf::from("src/main.rs"));
+
+        let mut config = config;
+        config.set_dotfile_prefix(Some("_".to_string()));
+        assert_eq!(config.get_output_path(&"_editorconfig"), PathBuf::from(".editorconfig"));
+        assert_eq!(config.get_output_path(&"pkg/__init__.py"), PathBuf::from("pkg/__init__.py"));
+
+        let invalid = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        dotfiles: dotdot_
+        "#);
+        assert!(matches!(invalid.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::InvalidDotfilePrefix(_))));
+    }
+
+    #[test]
+    pub fn merge_rules_are_parsed() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        merge:
+          Cargo.toml: append
+          "*.yml": union
+        "#).unwrap();
+
+        assert_eq!(config.get_merge_strategy(&"Cargo.toml"), Some(ArrayMergeStrategy::Append));
+        assert_eq!(config.get_merge_strategy(&"docker-compose.yml"), Some(ArrayMergeStrategy::Union));
+        assert_eq!(config.get_merge_strategy(&"README.md"), None);
+
+        let invalid = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        merge:
+          Cargo.toml: prepend
+        "#);
+        assert!(matches!(invalid.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::InvalidMergeArrayStrategy(name)) if name == "prepend"));
+    }
+
+    #[test]
+    pub fn hooks_are_parsed() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        hooks:
+          pre_render: "echo starting"
+          post_render:
+            - cargo fmt
+            - git init
+        "#).unwrap();
+
+        assert_eq!(config.get_pre_render_hooks(), &["echo starting".to_string()]);
+        assert_eq!(config.get_post_render_hooks(), &["cargo fmt".to_string(), "git init".to_string()]);
+
+        let invalid = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        hooks:
+          post_render: [[cargo, fmt]]
+        "#);
+        assert!(matches!(invalid.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::HookCommandMustBeAString)));
+    }
+
+    #[test]
+    pub fn inherit_from_merges_parent_config() {
+        let parent = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: "{{ crate_name }}"
+        include: "*"
+        exclude: "target/**"
+        dotfiles: dot_
+        hooks:
+          post_render: cargo fmt
+        variables:
+          crate_name: my-crate
+          edition: "2021"
+          license: MIT
+        "#).unwrap();
+
+        let mut child = parse_config_from_yaml_string(r#"
+        extends: rust-crate
+        exclude: "benches/**"
+        hooks:
+          post_render: git init
+        variables:
+          edition: "2024"
+          use_tokio: true
+        "#).unwrap();
+        assert_eq!(child.get_extends(), Some("rust-crate"));
+
+        child.inherit_from(&parent);
+        assert_eq!(child.get_output_type(), TemplateOutputType::Directory);
+        assert_eq!(child.get_output_directory(), Some("{{ crate_name }}"));
+        assert_eq!(child.get_dotfile_prefix(), Some("dot_"));
+        assert_eq!(child.get_post_render_hooks(), &["cargo fmt".to_string(), "git init".to_string()]);
+
+        let variable_items = child.get_variable_items();
+        let variable_names: Vec<&str> = variable_items.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(variable_names, vec!["crate_name", "edition", "license", "use_tokio"]);
+        assert_eq!(child.get_variable("edition").unwrap().get_default(), &Value::from("2024"));
+
+        assert!(child.should_include_file(&"src/lib.rs"));
+        assert!(!child.should_include_file(&"target/debug/app"));
+        assert!(!child.should_include_file(&"benches/bench.rs"));
+    }
+
+    #[test]
+    pub fn subtemplates_are_parsed() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        subtemplates:
+          - license
+          - template: ci
+            into: ".github/workflows"
+            vars:
+              project: "{{ crate_name }}"
+              use_cache: true
+        "#).unwrap();
+
+        let subtemplates = config.get_subtemplates();
+        assert_eq!(subtemplates.len(), 2);
+        assert_eq!(subtemplates[0], Subtemplate::new("license".to_string()));
+        assert_eq!(subtemplates[1].get_template(), "ci");
+        assert_eq!(subtemplates[1].get_into(), ".github/workflows");
+        assert_eq!(subtemplates[1].get_variables(), &[("project".to_string(), Value::from("{{ crate_name }}")), ("use_cache".to_string(), Value::from(true))]);
+
+        let invalid = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        subtemplates:
+          - into: "."
+        "#);
+        assert!(matches!(invalid.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::NoSubtemplateTemplate)));
+    }
+
+    #[test]
+    pub fn parse_config_works_for_inject_type_templates() {
+        let config = parse_config_from_yaml_string(r#"
+        type: inject
+        output:
+          filename: src/lib.rs
+          marker: "// utsusu: modules"
+          position: before
+        include: module.rs
+        "#).unwrap();
+
+        assert_eq!(config.get_output_type(), TemplateOutputType::Inject);
+        assert_eq!(config.get_output_filename(), Some("src/lib.rs"));
+        let injection = config.get_injection().unwrap();
+        assert_eq!(injection.get_position(), InjectPosition::Before);
+        assert!(injection.get_anchor().is_match("    // utsusu: modules"));
+        assert!(injection.get_end_anchor().is_none());
+
+        let missing_end = parse_config_from_yaml_string(r#"
+        type: inject
+        output:
+          filename: src/lib.rs
+          anchor: "^// begin"
+          position: replace_between
+        include: module.rs
+        "#);
+        assert!(matches!(missing_end.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::NoInjectEndAnchor)));
+
+        let missing_anchor = parse_config_from_yaml_string(r#"
+        type: inject
+        output:
+          filename: src/lib.rs
+        include: module.rs
+        "#);
+        assert!(matches!(missing_anchor.map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::NoInjectAnchor)));
+    }
+
+    #[test]
+    pub fn parse_errors_have_key_paths_and_locations() {
+        let invalid_type = parse_config_from_yaml_string("type: flie\noutput:\n  filename: out.rs\ninclude: in.rs\n").unwrap_err();
+        assert!(matches!(invalid_type.get_kind(), ConfigParseErrorKind::InvalidOutputType));
+        assert_eq!(invalid_type.get_key_path_string(), "type");
+        assert_eq!(invalid_type.get_location(), Some((1, 7)));
+        assert_eq!(invalid_type.to_string(), "type: the output type must be one of 'file', 'directory', or 'inject' (line 1, column 7)");
+
+        let invalid_choice = parse_config_from_yaml_string(r#"
+type: directory
+output:
+  directory: out
+include: "*"
+variables:
+  license:
+    choices:
+      - MIT
+      - [Apache-2.0]
+"#).unwrap_err();
+        assert!(matches!(invalid_choice.get_kind(), ConfigParseErrorKind::VariableChoiceMustBeAScalar));
+        assert_eq!(invalid_choice.get_key_path(), &[ConfigKey::from("variables"), ConfigKey::from("license"), ConfigKey::from("choices"), ConfigKey::Index(1)]);
+        assert_eq!(invalid_choice.get_key_path_string(), "variables.license.choices[1]");
+        assert_eq!(invalid_choice.get_location(), Some((10, 10)));
+
+        // Missing keys point at the key of the mapping they're missing from
+        let missing_filename = parse_config_from_yaml_string("type: file\noutput:\n  directory: out\ninclude: in.rs\n").unwrap_err();
+        assert!(matches!(missing_filename.get_kind(), ConfigParseErrorKind::NoOutputFilename));
+        assert_eq!(missing_filename.get_location(), Some((2, 1)));
+
+        let invalid_yaml = parse_config_from_yaml_string("type: [file\n").unwrap_err();
+        assert!(matches!(invalid_yaml.get_kind(), ConfigParseErrorKind::YamlParseError(_)));
+        assert!(invalid_yaml.get_key_path().is_empty());
+        assert!(invalid_yaml.get_location().is_some());
+    }
+
+    #[test]
+    pub fn configs_round_trip_through_yaml() {
+        let config = parse_config_from_yaml_string(r#"
+type: directory
+output:
+  directory: "{{ crate_name }}"
+include:
+  - "**"
+  - "!target/**"
+  - "\\!important.md"
+exclude: "*.bak"
+copy_without_render: "*.png"
+files:
+  "ci/**": "use_ci"
+rename:
+  "dot_env": ".env"
+  "re:^src/(\\w+)\\.tpl$": "src/$1.rs"
+merge:
+  "Cargo.toml": union
+dotfiles: dot_
+subtemplates:
+  - license
+  - template: ci
+    into: .ci
+    vars:
+      cache: true
+hooks:
+  post_render: cargo fmt
+variables:
+  crate_name: demo
+  edition: "2024"
+  license:
+    choices: [MIT, Apache-2.0]
+    prompt: License
+    group: Legal
+  features:
+    choices: [serde, cli]
+    multiple: true
+    default: [serde]
+  use_ci:
+    default: false
+    advanced: true
+  version:
+    default: 0.1.0
+    when: "use_ci"
+    validate:
+      required: true
+      kind: semver
+      pattern: "^0\\."
+      max_length: 10
+      min: 0
+"#).unwrap();
+
+        let yaml = config.to_yaml_string();
+        let reparsed = parse_config_from_yaml_string(&yaml).unwrap();
+        assert_eq!(reparsed.to_yaml_string(), yaml);
+        assert_eq!(reparsed.to_value(), config.to_value());
+
+        // The globs are written as they were given, in the lists they were given in
+        assert_eq!(config.to_value()["include"], serde_json::json!(["**", "!target/**", "\\!important.md"]));
+        assert_eq!(config.to_value()["exclude"], serde_json::json!(["*.bak"]));
+        assert_eq!(config.to_value()["rename"]["re:^src/(\\w+)\\.tpl$"], Value::from("src/$1.rs"));
+        assert!(reparsed.should_include_file(&"!important.md"));
+        assert!(!reparsed.should_include_file(&"target/debug/out"));
+        assert_eq!(reparsed.get_output_path(&"src/main.tpl"), PathBuf::from("src/main.rs"));
+        assert_eq!(reparsed.get_render_context(None).unwrap(), config.get_render_context(None).unwrap());
+        assert_eq!(reparsed.get_variable("edition").unwrap().get_default(), &Value::from("2024"));
+        assert_eq!(reparsed.get_variable("version").unwrap(), config.get_variable("version").unwrap());
+
+        // Variables that are just a default stay in the short form
+        assert_eq!(config.to_value()["variables"]["crate_name"], Value::from("demo"));
+        assert!(format!("{:?}", config).contains("\"!target/**\""));
+
+        // Separate include and exclude lists are written back separately
+        let yaml = "type: directory\noutput:\n  directory: out\ninclude:\n  - src/**\n  - \"!src/tmp/**\"\nexclude:\n  - \"*.bak\"\n  - \"!keep.bak\"\n  - \"\\\\!odd\"\n";
+        let config = parse_config_from_yaml_string(yaml).unwrap();
+        assert_eq!(config.to_yaml_string(), yaml);
+        assert_eq!(parse_config_from_yaml_string(&config.to_yaml_string()).unwrap().to_yaml_string(), yaml);
+
+        // Once patterns from an exclude list are followed by others, everything goes in the include list, in order
+        let mut child = parse_config_from_yaml_string("extends: base\ninclude: \"*.md\"\n").unwrap();
+        child.inherit_from(&config);
+        assert_eq!(child.to_value()["include"], serde_json::json!(["src/**", "!src/tmp/**", "!*.bak", "keep.bak", "!!odd", "*.md"]));
+        assert_eq!(child.to_value().get("exclude"), None);
+
+        // A template that takes its output from its parent leaves it out
+        let child = parse_config_from_yaml_string("extends: base\n").unwrap();
+        assert_eq!(child.to_yaml_string(), "extends: base\n");
+    }
+
+    #[test]
+    pub fn inline_configs_are_parsed_from_each_document() {
+        let collection = r#"
+name: module
+type: file
+output:
+  filename: "{{ name }}.rs"
+body: |
+  pub fn {{ name }}() {}
+variables:
+  name: run
+---
+name: route
+type: inject
+output:
+  filename: src/main.rs
+  marker: "// routes"
+body: "route!({{ path }});"
+"#;
+        let inline_configs = parse_inline_configs_from_yaml_string(collection).unwrap();
+        assert_eq!(inline_configs.len(), 2);
+        assert_eq!(inline_configs[0].get_name(), "module");
+        assert_eq!(inline_configs[0].get_body(), "pub fn {{ name }}() {}\n");
+        assert!(inline_configs[0].get_config().should_include_file(&"module"));
+        assert_eq!(inline_configs[1].get_config().get_output_type(), TemplateOutputType::Inject);
+
+        // Errors are located in the document they're in
+        let invalid_filename = parse_inline_configs_from_yaml_string("name: a\ntype: file\noutput:\n  filename: a\nbody: a\n---\nname: b\ntype: file\noutput:\n  filename: 3\nbody: b\n").unwrap_err();
+        assert!(matches!(invalid_filename.get_kind(), ConfigParseErrorKind::InvalidOutputFilename));
+        assert_eq!(invalid_filename.get_location(), Some((10, 13)));
+
+        let duplicate = parse_inline_configs_from_yaml_string("name: a\ntype: file\noutput:\n  filename: a\nbody: a\n---\nname: a\ntype: file\noutput:\n  filename: b\nbody: b\n").unwrap_err();
+        assert!(matches!(duplicate.get_kind(), ConfigParseErrorKind::DuplicateInlineTemplateName(_)));
+        assert_eq!(duplicate.get_location(), Some((7, 7)));
+
+        let directory = parse_inline_configs_from_yaml_string("name: a\ntype: directory\noutput:\n  directory: a\nbody: a\n").unwrap_err();
+        assert!(matches!(directory.get_kind(), ConfigParseErrorKind::InlineTemplateMustBeAFile));
+        let unnamed = parse_inline_configs_from_yaml_string("type: file\noutput:\n  filename: a\nbody: a\n").unwrap_err();
+        assert!(matches!(unnamed.get_kind(), ConfigParseErrorKind::NoInlineTemplateName));
+
+        // An empty file is an error rather than a panic, and is an empty collection
+        assert!(matches!(parse_config_from_yaml_string("").map_err(ConfigParseError::into_kind), Err(ConfigParseErrorKind::NoConfig)));
+        assert!(parse_inline_configs_from_yaml_string("").unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn builder_checks_configs_like_the_parser() {
+        let mut license = TemplateVariable::new("license".to_string(), Value::from("MIT"));
+        license.set_choices(Some(vec![Value::from("MIT"), Value::from("Apache-2.0")]));
+        let config = TemplateConfig::builder()
+            .file("{{ name }}.rs")
+            .include("*.rs")
+            .exclude("generated.rs")
+            .variable(&TemplateVariable::new("name".to_string(), Value::from("main")))
+            .variable(&license)
+            .post_render_hook("cargo fmt")
+            .build()
+            .unwrap();
+        assert_eq!(config.get_output_type(), TemplateOutputType::File);
+        assert_eq!(config.get_output_filename(), Some("{{ name }}.rs"));
+        assert!(config.should_include_file(&"lib.rs"));
+        assert!(!config.should_include_file(&"generated.rs"));
+        assert_eq!(config.get_variable("license").unwrap(), &license);
+        assert_eq!(config.get_post_render_hooks(), &["cargo fmt".to_string()]);
+
+        let mut between = Injection::new(Regex::new("begin").unwrap(), InjectPosition::ReplaceBetween);
+        between.set_end_anchor(Some(Regex::new("end").unwrap()));
+        let injected = TemplateConfig::builder().inject("src/lib.rs", &between).include("mod.rs").build().unwrap();
+        assert_eq!(injected.get_injection().unwrap().get_end_anchor().map(Regex::as_str), Some("end"));
+
+        // The same things are wrong as in a configuration file, with the same key paths
+        let too_many_globs = TemplateConfig::builder().file("out.rs").include("a.rs").include("b.rs").build().unwrap_err();
+        assert!(matches!(too_many_globs.get_kind(), ConfigParseErrorKind::TooManyIncludedFileGlobs));
+        let no_type = TemplateConfig::builder().include("*").build().unwrap_err();
+        assert!(matches!(no_type.get_kind(), ConfigParseErrorKind::NoOutputType));
+
+        // Only one kind of output can be set, though setting the same kind again replaces it
+        let file_and_directory = TemplateConfig::builder().file("a").directory("b").include("*").build().unwrap_err();
+        assert!(matches!(file_and_directory.get_kind(), ConfigParseErrorKind::ConflictingOutputTypes(first, second) if first == "file" && second == "directory"));
+        assert_eq!(file_and_directory.get_key_path_string(), "type");
+        let file_and_inject = TemplateConfig::builder().file("a").inject("src/lib.rs", &between).include("*").build().unwrap_err();
+        assert!(matches!(file_and_inject.get_kind(), ConfigParseErrorKind::ConflictingOutputTypes(_, _)));
+        let refiled = TemplateConfig::builder().file("a").file("b").include("*").build().unwrap();
+        assert_eq!(refiled.get_output_filename(), Some("b"));
+        let invalid_glob = TemplateConfig::builder().directory("out").include("*").copy_without_render("[").build().unwrap_err();
+        assert_eq!(invalid_glob.get_key_path_string(), "copy_without_render[0]");
+        license.set_choices(Some(vec![Value::from("Apache-2.0")]));
+        let invalid_default = TemplateConfig::builder().directory("out").include("*").variable(&license).build().unwrap_err();
+        assert!(matches!(invalid_default.get_kind(), ConfigParseErrorKind::VariableDefaultNotInChoices(_)));
+
+        // Templates that extend another can leave everything out
+        assert_eq!(TemplateConfig::builder().extends("base").build().unwrap().get_extends(), Some("base"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn configs_serialize_and_deserialize_like_files() {
+        let config = parse_config_from_yaml_string("type: file\noutput:\n  filename: out.rs\ninclude: in.rs\nvariables:\n  name: demo\n").unwrap();
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, r#"{"type":"file","output":{"filename":"out.rs"},"include":["in.rs"],"variables":{"name":"demo"}}"#);
+
+        let deserialized: TemplateConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.to_value(), config.to_value());
+
+        let invalid = serde_json::from_str::<TemplateConfig>(r#"{"type":"flie"}"#).unwrap_err();
+        assert!(invalid.to_string().contains("type: the output type must be one of"));
+    }
+
+    #[test]
+    pub fn toml_and_json_configs_are_parsed_like_yaml() {
+        let toml_config = parse_config_from_toml_string(r#"
+type = "directory"
+include = ["*"]
+exclude = "*.bak"
+
+[output]
+directory = "{{ crate_name }}"
+
+[variables.crate_name]
+default = "demo"
+choices = ["demo", "other"]
+
+[variables.edition]
+default = 2024
+"#).unwrap();
+        let yaml_config = parse_config_from_yaml_string(r#"
+type: directory
+include: ["*"]
+exclude: "*.bak"
+output:
+  directory: "{{ crate_name }}"
+variables:
+  crate_name:
+    default: demo
+    choices: [demo, other]
+  edition:
+    default: 2024
+"#).unwrap();
+        assert_eq!(toml_config.get_output_type(), TemplateOutputType::Directory);
+        assert_eq!(toml_config.get_render_context(None).unwrap(), yaml_config.get_render_context(None).unwrap());
+
+        let json_config = parse_config_from_json_string(r#"{
+  "type": "file",
+  "output": {"filename": "LICENSE"},
+  "include": "LICENSE",
+  "variables": {"year": {"default": 2024}, "holder": "Acme"}
+}"#).unwrap();
+        assert_eq!(json_config.get_output_filename(), Some("LICENSE"));
+        assert_eq!(json_config.get_render_context(None).unwrap().get("year"), Some(&Value::from(2024)));
+
+        // Errors have the same key paths in every format, and are located in the original text
+        let invalid_toml_choice = parse_config_from_toml_string("type = \"file\"\ninclude = \"x\"\n\n[output]\nfilename = \"x\"\n\n[variables.license]\nchoices = [\"MIT\", [\"Apache-2.0\"]]\n").unwrap_err();
+        assert!(matches!(invalid_toml_choice.get_kind(), ConfigParseErrorKind::VariableChoiceMustBeAScalar));
+        assert_eq!(invalid_toml_choice.get_key_path_string(), "variables.license.choices[1]");
+        assert_eq!(invalid_toml_choice.get_location(), Some((8, 19)));
+
+        let missing_toml_filename = parse_config_from_toml_string("type = \"file\"\ninclude = \"x\"\n\n[output]\ndirectory = \"x\"\n").unwrap_err();
+        assert!(matches!(missing_toml_filename.get_kind(), ConfigParseErrorKind::NoOutputFilename));
+        assert_eq!(missing_toml_filename.get_location(), Some((4, 1)));
+
+        let invalid_json_type = parse_config_from_json_string("{\n  \"type\": \"flie\"\n}").unwrap_err();
+        assert!(matches!(invalid_json_type.get_kind(), ConfigParseErrorKind::InvalidOutputType));
+        assert_eq!(invalid_json_type.get_location(), Some((2, 11)));
+
+        let invalid_toml = parse_config_from_toml_string("type = \"file\"\noutput = {\n").unwrap_err();
+        assert!(matches!(invalid_toml.get_kind(), ConfigParseErrorKind::TomlParseError(_)));
+        assert_eq!(invalid_toml.get_location().map(|(line, _)| line), Some(2));
+
+        let invalid_json = parse_config_from_json_string("{\n  \"type\": file\n}").unwrap_err();
+        assert!(matches!(invalid_json.get_kind(), ConfigParseErrorKind::JsonParseError(_)));
+        assert_eq!(invalid_json.get_location().map(|(line, _)| line), Some(2));
+        assert!(!invalid_json.to_string().contains(" at line "));
+    }
+
     #[test]
     pub fn config_set_output_type_works() {
         let mut config = TemplateConfig::new();
@@ -0,0 +1,192 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::LazyLock;
+use regex::Regex;
+
+// Work out what went wrong when a template failed to render, in terms of the template's source, and
+// point at where in a source (a template or a configuration file) the problem is
+
+/// Matches the message Tera gives when a variable isn't in the context
+static UNDEFINED_VARIABLE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Variable `([^`]+)` not found in context").unwrap());
+
+/// Matches the text Tera quotes in its messages, which is usually the offending expression
+static QUOTED_EXPRESSION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`([^`]+)`").unwrap());
+
+/// Matches the tags in a template that expressions can appear in
+static TEMPLATE_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)\{\{.*?\}\}|\{%.*?%\}").unwrap());
+
+/// A description of a failed render, pointing at the offending expression in the template where
+/// possible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderDiagnostic {
+    /// The name of the template that failed to render
+    template_name: String,
+
+    /// The messages of the error and each of the errors that caused it, outermost first
+    messages: Vec<String>,
+
+    /// The variable that isn't defined, if that's why rendering failed
+    undefined_variable: Option<String>,
+
+    /// Where the offending expression is in the template: (line, column, length in characters),
+    /// with lines and columns starting at 1
+    location: Option<(usize, usize, usize)>,
+
+    /// The lines of the template around the offending expression, with it highlighted
+    snippet: Option<String>,
+}
+
+impl RenderDiagnostic {
+    pub fn get_template_name(&self) -> &str {
+        &self.template_name
+    }
+
+    pub fn get_messages(&self) -> &[String] {
+        &self.messages
+    }
+
+    pub fn get_undefined_variable(&self) -> Option<&str> {
+        self.undefined_variable.as_deref()
+    }
+
+    pub fn get_location(&self) -> Option<(usize, usize, usize)> {
+        self.location
+    }
+
+    pub fn get_snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+}
+
+impl fmt::Display for RenderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.messages.join(": "))?;
+        if let Some((line, column, _)) = self.location {
+            write!(f, "\n  --> {}:{}:{}", self.template_name, line, column)?;
+        }
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        if let Some(variable_name) = &self.undefined_variable {
+            write!(f, "\nUndefined variable: {}", variable_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Describes `tera_error`, which happened while rendering the template `template_name`. Its chain
+/// of causes is followed to find the offending expression, which is looked for in `source` (the
+/// unrendered template) if it's given.
+pub fn diagnose_render_error(template_name: &str, tera_error: &tera::Error, source: Option<&str>) -> RenderDiagnostic {
+    let mut messages = vec![tera_error.to_string()];
+    let mut cause = tera_error.source();
+    while let Some(cause_error) = cause {
+        messages.push(cause_error.to_string());
+        cause = cause_error.source();
+    }
+
+    let undefined_variable = messages.iter()
+        .find_map(|message| UNDEFINED_VARIABLE_REGEX.captures(message))
+        .map(|captures| captures[1].to_string());
+
+    // The innermost error is the most specific about what went wrong
+    let expression = undefined_variable.clone().or_else(|| messages.iter().rev()
+        .find_map(|message| QUOTED_EXPRESSION_REGEX.captures(message))
+        .map(|captures| captures[1].to_string()));
+
+    let location = match (source, &expression) {
+        (Some(source), Some(expression)) => find_expression(source, expression),
+        _ => None,
+    };
+    let snippet = match (source, location) {
+        (Some(source), Some((line, column, length))) => get_source_snippet(source, line, column, length),
+        _ => None,
+    };
+
+    RenderDiagnostic {
+        template_name: template_name.to_string(),
+        messages,
+        undefined_variable,
+        location,
+        snippet,
+    }
+}
+
+/// Finds the first use of `expression` inside a tag in `source`, returning its (line, column,
+/// length in characters).
+fn find_expression(source: &str, expression: &str) -> Option<(usize, usize, usize)> {
+    // Only whole expressions count, so `name` isn't found in `crate_name` or `user.name`
+    let expression_regex = Regex::new(&format!(r"(?:^|[^\w.])({})(?:$|\W)", regex::escape(expression))).ok()?;
+    let offset = TEMPLATE_TAG_REGEX.find_iter(source).find_map(|tag| {
+        expression_regex.captures(tag.as_str()).map(|captures| tag.start() + captures.get(1).map_or(0, |m| m.start()))
+    })?;
+
+    let (line, column) = get_line_and_column(source, offset);
+    Some((line, column, expression.chars().count()))
+}
+
+/// Returns the line and column (both starting at 1) of the character at `byte_offset` in `source`.
+pub fn get_line_and_column(source: &str, byte_offset: usize) -> (usize, usize) {
+    let byte_offset = byte_offset.min(source.len());
+    let prefix = source.get(..byte_offset).unwrap_or(source);
+    let line_start = prefix.rfind('\n').map_or(0, |i| i + 1);
+    (prefix.matches('\n').count() + 1, prefix[line_start..].chars().count() + 1)
+}
+
+/// Returns line `line` of `source` with carets under the `width` characters starting at column
+/// `column` (both starting at 1), in the style of a compiler diagnostic, preceded by the line before
+/// it for context. Returns None if `source` doesn't have that line.
+pub fn get_source_snippet(source: &str, line: usize, column: usize, width: usize) -> Option<String> {
+    let lines: Vec<&str> = source.lines().collect();
+    if line == 0 || line > lines.len() {
+        return None;
+    }
+
+    let gutter_width = line.to_string().len();
+    let mut snippet = String::new();
+    for line_number in line.saturating_sub(1).max(1)..=line {
+        snippet.push_str(&format!("{:>width$} | {}\n", line_number, lines[line_number - 1], width = gutter_width));
+    }
+
+    // Tabs are kept in the padding, so the carets line up however wide they're shown
+    let padding: String = lines[line - 1].chars().take(column.saturating_sub(1)).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    snippet.push_str(&format!("{:>width$} | {}{}", "", padding, "^".repeat(width.max(1)), width = gutter_width));
+    Some(snippet)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tera::{Context, Tera};
+
+    #[test]
+    pub fn diagnose_render_error_finds_undefined_variables() {
+        let source = "[package]\nname = \"{{ crate_name }}\"\nedition = \"{{ edition }}\"\n";
+        let mut tera = Tera::default();
+        tera.add_raw_template("Cargo.toml", source).unwrap();
+
+        let mut context = Context::new();
+        context.insert("crate_name", "demo");
+        let tera_error = tera.render("Cargo.toml", &context).unwrap_err();
+
+        let diagnostic = diagnose_render_error("Cargo.toml", &tera_error, Some(source));
+        assert_eq!(diagnostic.get_undefined_variable(), Some("edition"));
+        assert_eq!(diagnostic.get_location(), Some((3, 15, 7)));
+        assert_eq!(diagnostic.get_snippet(), Some("2 | name = \"{{ crate_name }}\"\n3 | edition = \"{{ edition }}\"\n  |               ^^^^^^^"));
+        assert!(diagnostic.get_messages().len() > 1);
+        assert!(diagnostic.to_string().contains("--> Cargo.toml:3:15"));
+
+        // Without the source, there's nothing to point at
+        let diagnostic = diagnose_render_error("Cargo.toml", &tera_error, None);
+        assert_eq!(diagnostic.get_undefined_variable(), Some("edition"));
+        assert_eq!(diagnostic.get_location(), None);
+    }
+
+    #[test]
+    pub fn find_expression_matches_whole_expressions_in_tags() {
+        let source = "name\n{{ crate_name }} {% if name %}{{ name | upper }}{% endif %}";
+        assert_eq!(find_expression(source, "name"), Some((2, 24, 4)));
+        assert_eq!(find_expression(source, "missing"), None);
+    }
+}
@@ -0,0 +1,297 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+use crate::template_config::{ArrayMergeStrategy, TemplateConfig};
+use crate::template_rendering::render_path;
+use crate::template_rendering::merge_render::{merge_documents, MergeError};
+
+// Take a template configuration for a directory tree and render it out
+
+/// A single file produced by rendering a directory template.
+#[derive(Debug, Clone)]
+pub struct RenderedFile {
+    /// The name of the Tera template this file was rendered from, or for files copied verbatim, the
+    /// path of the file it was copied from.
+    pub template_name: String,
+
+    /// Where the file should be written, relative to the output directory. Any variables used in
+    /// the source path have already been rendered.
+    pub relative_path: PathBuf,
+
+    /// The rendered contents of the file, or the unchanged source contents for files copied verbatim.
+    pub contents: Vec<u8>,
+
+    /// Whether the file was copied without being rendered.
+    pub verbatim: bool,
+
+    /// If the file is merged into an existing file at its output path, how arrays are combined.
+    pub merge: Option<ArrayMergeStrategy>,
+}
+
+#[derive(Debug)]
+pub enum DirectoryRenderError {
+    /// Building the render context failed, e.g. because a computed default couldn't be rendered
+    ContextError(tera::Error),
+    /// Rendering the output path for a template failed: (raw path, error)
+    PathRenderError(String, tera::Error),
+    /// Rendering a template's contents failed: (template name, error)
+    TemplateRenderError(String, tera::Error),
+    /// Evaluating the condition for a file failed: (source path, error)
+    ConditionError(PathBuf, tera::Error),
+    /// A file to copy verbatim could not be read: (file, error)
+    ReadError(PathBuf, std::io::Error),
+    /// The output directory already exists, and will not be overwritten
+    OutputDirectoryExists(PathBuf),
+    /// A directory could not be created: (directory, error)
+    CreateDirectoryError(PathBuf, std::io::Error),
+    /// A rendered file could not be written: (file, error)
+    WriteError(PathBuf, std::io::Error),
+    /// A rendered file could not be merged into the existing file: (file, error)
+    MergeError(PathBuf, MergeError),
+}
+
+impl fmt::Display for DirectoryRenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirectoryRenderError::ContextError(tera_error) => write!(f, "could not build the render context: {}", tera_error),
+            DirectoryRenderError::PathRenderError(path, tera_error) => write!(f, "could not render path '{}': {}", path, tera_error),
+            DirectoryRenderError::TemplateRenderError(template_name, tera_error) => write!(f, "could not render template file '{}': {}", template_name, tera_error),
+            DirectoryRenderError::ConditionError(source_path, tera_error) => write!(f, "could not evaluate the condition for file '{}': {}", source_path.display(), tera_error),
+            DirectoryRenderError::ReadError(source_path, read_error) => write!(f, "could not read template file '{}': {}", source_path.display(), read_error),
+            DirectoryRenderError::OutputDirectoryExists(directory) => write!(f, "directory '{}' already exists", directory.display()),
+            DirectoryRenderError::CreateDirectoryError(directory, mkdir_error) => write!(f, "could not create directory '{}': {}", directory.display(), mkdir_error),
+            DirectoryRenderError::WriteError(file, write_error) => write!(f, "could not write '{}': {}", file.display(), write_error),
+            DirectoryRenderError::MergeError(file, merge_error) => write!(f, "could not merge into '{}': {}", file.display(), merge_error),
+        }
+    }
+}
+
+/// Renders every template loaded into `tera` using the variables from `config`, overridden by any
+/// values in `context`, and reads each of the `verbatim_files` so they can be copied as-is. These
+/// are given as (path relative to the files directory, path to copy from) pairs.
+///
+/// Template names are treated as paths; names underneath `files_root` have that prefix stripped, and
+/// any other names are used as-is, to determine the path of each file relative to the output
+/// directory, which is then rewritten by the rename rules and dotfile prefix in
+/// `config`. The paths of verbatim files are still rendered. Files whose condition in
+/// `config` is false are skipped. Nothing is written to disk; see [write_rendered_directory] for that.
+///
+/// Files are returned sorted by their path relative to the files directory.
+pub fn render_directory<P: AsRef<Path>>(tera: &Tera, config: &TemplateConfig, files_root: &P, verbatim_files: &[(PathBuf, PathBuf)], context: Option<&Context>) -> Result<Vec<RenderedFile>, DirectoryRenderError> {
+    let full_context = match config.get_render_context(context) {
+        Err(tera_error) => return Err(DirectoryRenderError::ContextError(tera_error)),
+        Ok(full_context) => full_context,
+    };
+
+    // Every source file by its path relative to the files directory, along with the file to copy
+    // from for verbatim files
+    let mut sources: Vec<(PathBuf, String, Option<&Path>)> = tera.get_template_names()
+        .map(|name| {
+            let template_path = Path::new(name);
+            (template_path.strip_prefix(files_root).unwrap_or(template_path).to_path_buf(), name.to_string(), None)
+        })
+        .collect();
+    sources.extend(verbatim_files.iter().map(|(relative_path, source_path)| (relative_path.clone(), source_path.display().to_string(), Some(source_path.as_path()))));
+    sources.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut rendered_files = Vec::with_capacity(sources.len());
+    for (files_root_relative_path, template_name, verbatim_source) in sources {
+
+        match config.is_file_enabled(&files_root_relative_path, &full_context) {
+            Err(tera_error) => return Err(DirectoryRenderError::ConditionError(files_root_relative_path, tera_error)),
+            Ok(false) => continue,
+            Ok(true) => {},
+        };
+
+        // Render the path (after applying any rename rules), in case a file or directory was named
+        // using a variable
+        let relative_path_raw = config.get_output_path(&files_root_relative_path).display().to_string();
+        let relative_path = match render_path(&relative_path_raw, &full_context) {
+            Err(tera_error) => return Err(DirectoryRenderError::PathRenderError(relative_path_raw, tera_error)),
+            Ok(path) => path,
+        };
+
+        let contents = match verbatim_source {
+            Some(source_path) => match std::fs::read(source_path) {
+                Err(read_error) => return Err(DirectoryRenderError::ReadError(source_path.to_path_buf(), read_error)),
+                Ok(contents) => contents,
+            },
+            None => match tera.render(&template_name, &full_context) {
+                Err(tera_error) => return Err(DirectoryRenderError::TemplateRenderError(template_name, tera_error)),
+                Ok(contents) => contents.into_bytes(),
+            },
+        };
+
+        rendered_files.push(RenderedFile {
+            template_name,
+            relative_path,
+            contents,
+            verbatim: verbatim_source.is_some(),
+            merge: config.get_merge_strategy(&files_root_relative_path),
+        });
+    }
+
+    Ok(rendered_files)
+}
+
+/// Creates `output_directory` and writes all the rendered files into it.
+///
+/// Errors if the output directory already exists. Returns the paths of all the files written.
+pub fn write_rendered_directory<P: AsRef<Path>>(output_directory: &P, rendered_files: &[RenderedFile]) -> Result<Vec<PathBuf>, DirectoryRenderError> {
+    create_output_directory(output_directory)?;
+    write_rendered_files(output_directory, rendered_files)
+}
+
+/// Creates `output_directory` (and all its parent directories), erroring if it already exists.
+pub fn create_output_directory<P: AsRef<Path>>(output_directory: &P) -> Result<(), DirectoryRenderError> {
+    let output_directory = output_directory.as_ref();
+
+    // -- Verify it doesn't already exist
+    if output_directory.exists() {
+        return Err(DirectoryRenderError::OutputDirectoryExists(output_directory.to_path_buf()));
+    }
+
+    // -- Create the directory (and all parent directories)
+    if let Err(fs_error) = std::fs::create_dir_all(output_directory) {
+        return Err(DirectoryRenderError::CreateDirectoryError(output_directory.to_path_buf(), fs_error));
+    }
+
+    Ok(())
+}
+
+/// Writes all the rendered files into `output_directory`, which should already exist. Files that
+/// are merged into an existing file are left alone if merging changes nothing.
+///
+/// Returns the paths of all the files written.
+pub fn write_rendered_files<P: AsRef<Path>>(output_directory: &P, rendered_files: &[RenderedFile]) -> Result<Vec<PathBuf>, DirectoryRenderError> {
+    let output_directory = output_directory.as_ref();
+
+    // Write all the files to the output directory
+    let mut written_files = Vec::with_capacity(rendered_files.len());
+    for rendered_file in rendered_files {
+        let output_file_path = output_directory.join(&rendered_file.relative_path);
+
+        // -- Ensure the parent directories exist
+        if let Some(final_directory) = output_file_path.parent()
+            && !final_directory.exists()
+            && let Err(mkdir_error) = std::fs::create_dir_all(final_directory)
+        {
+            return Err(DirectoryRenderError::CreateDirectoryError(final_directory.to_path_buf(), mkdir_error));
+        }
+
+        // -- Merge the rendered file into the existing one, if there is one and it should be
+        let contents = match rendered_file.merge {
+            Some(array_strategy) if output_file_path.is_file() => {
+                let existing_contents = match std::fs::read_to_string(&output_file_path) {
+                    Err(read_error) => return Err(DirectoryRenderError::ReadError(output_file_path, read_error)),
+                    Ok(existing_contents) => existing_contents,
+                };
+                match merge_documents(&output_file_path, &existing_contents, &String::from_utf8_lossy(&rendered_file.contents), array_strategy) {
+                    Err(merge_error) => return Err(DirectoryRenderError::MergeError(output_file_path, merge_error)),
+                    Ok(None) => continue,
+                    Ok(Some(merged_contents)) => Cow::Owned(merged_contents.into_bytes()),
+                }
+            },
+            _ => Cow::Borrowed(rendered_file.contents.as_slice()),
+        };
+
+        // -- Write the rendered file
+        if let Err(write_error) = std::fs::write(&output_file_path, contents) {
+            return Err(DirectoryRenderError::WriteError(output_file_path, write_error));
+        }
+
+        written_files.push(output_file_path);
+    }
+
+    Ok(written_files)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::template_config::parse_config_from_yaml_string;
+    use crate::test_utils::TestDirectory;
+
+    const DIRECTORY_CONFIG: &str = r#"
+    type: directory
+    output:
+      directory: out
+    include: "*"
+    variables:
+      name: world
+      module: greeting
+    "#;
+
+    fn test_tera() -> Tera {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("/templates/files/README.md", "Hello, {{ name }}!"),
+            ("/templates/files/src/{{ module }}.rs", "// {{ module }}"),
+        ]).unwrap();
+        tera
+    }
+
+    #[test]
+    pub fn render_directory_renders_paths_and_contents() {
+        let config = parse_config_from_yaml_string(DIRECTORY_CONFIG).unwrap();
+        let rendered = render_directory(&test_tera(), &config, &"/templates/files", &[], None).unwrap();
+
+        assert_eq!(rendered.len(), 2);
+        assert_eq!(rendered[0].relative_path, PathBuf::from("README.md"));
+        assert_eq!(rendered[0].contents, b"Hello, world!");
+        assert_eq!(rendered[1].relative_path, PathBuf::from("src/greeting.rs"));
+        assert_eq!(rendered[1].contents, b"// greeting");
+    }
+
+    #[test]
+    pub fn render_directory_applies_override_context() {
+        let config = parse_config_from_yaml_string(DIRECTORY_CONFIG).unwrap();
+        let mut context = Context::new();
+        context.insert("module", "farewell");
+
+        let rendered = render_directory(&test_tera(), &config, &"/templates/files", &[], Some(&context)).unwrap();
+        assert_eq!(rendered[1].relative_path, PathBuf::from("src/farewell.rs"));
+        assert_eq!(rendered[1].contents, b"// farewell");
+    }
+
+    #[test]
+    pub fn render_directory_skips_disabled_files() {
+        let config = parse_config_from_yaml_string(r#"
+        type: directory
+        output:
+          directory: out
+        include: "*"
+        files:
+          src: "{{ with_source }}"
+        variables:
+          name: world
+          module: greeting
+          with_source: false
+        "#).unwrap();
+
+        let rendered = render_directory(&test_tera(), &config, &"/templates/files", &[], None).unwrap();
+        assert_eq!(rendered.len(), 1);
+        assert_eq!(rendered[0].relative_path, PathBuf::from("README.md"));
+
+        let mut context = Context::new();
+        context.insert("with_source", &true);
+        let rendered = render_directory(&test_tera(), &config, &"/templates/files", &[], Some(&context)).unwrap();
+        assert_eq!(rendered.len(), 2);
+    }
+
+    #[test]
+    pub fn render_directory_copies_verbatim_files() {
+        let files_root = TestDirectory::new("verbatim");
+        let image_path = files_root.write("assets/{{ module }}.png", [0x89, b'P', b'N', b'G', 0x00, 0xff]);
+
+        let config = parse_config_from_yaml_string(DIRECTORY_CONFIG).unwrap();
+        let rendered = render_directory(&Tera::default(), &config, &files_root.get_path(), &[(PathBuf::from("assets/{{ module }}.png"), image_path)], None);
+        let rendered = rendered.unwrap();
+        assert_eq!(rendered.len(), 1);
+        assert!(rendered[0].verbatim);
+        assert_eq!(rendered[0].relative_path, PathBuf::from("assets/greeting.png"));
+        assert_eq!(rendered[0].contents, [0x89, b'P', b'N', b'G', 0x00, 0xff]);
+    }
+}
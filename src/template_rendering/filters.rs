@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use tera::{try_get_value, Tera, Value};
+
+// Tera filters for changing the case of names, and other things that almost every template needs
+
+/// Nouns whose plurals don't follow the usual rules: (singular, plural)
+const IRREGULAR_NOUNS: [(&str, &str); 30] = [
+    ("person", "people"),
+    ("child", "children"),
+    ("man", "men"),
+    ("woman", "women"),
+    ("mouse", "mice"),
+    ("goose", "geese"),
+    ("foot", "feet"),
+    ("tooth", "teeth"),
+    ("index", "indices"),
+    ("datum", "data"),
+    ("quiz", "quizzes"),
+    ("knife", "knives"),
+    ("wife", "wives"),
+    ("life", "lives"),
+    ("wolf", "wolves"),
+    ("half", "halves"),
+    ("calf", "calves"),
+    ("shelf", "shelves"),
+    ("self", "selves"),
+    ("elf", "elves"),
+    ("leaf", "leaves"),
+    ("loaf", "loaves"),
+    ("thief", "thieves"),
+    ("analysis", "analyses"),
+    ("crisis", "crises"),
+    ("thesis", "theses"),
+    ("hypothesis", "hypotheses"),
+    ("diagnosis", "diagnoses"),
+    ("synopsis", "synopses"),
+    ("parenthesis", "parentheses"),
+];
+
+/// Nouns that are the same in the singular and the plural
+const UNCOUNTABLE_NOUNS: [&str; 8] = ["sheep", "fish", "deer", "series", "species", "news", "information", "metadata"];
+
+/// Nouns ending in "ie", whose plurals look like those of nouns ending in a consonant and "y"
+const IE_NOUNS: [&str; 20] = [
+    "movie", "cookie", "pie", "tie", "lie", "die", "zombie", "rookie", "calorie", "selfie", "hoodie", "genie", "prairie",
+    "newbie", "goalie", "smoothie", "brownie", "freebie", "auntie", "sortie",
+];
+
+/// Nouns ending in "e", whose plurals look like those of nouns that take "es"
+const E_NOUNS: [&str; 27] = [
+    "cache", "niche", "ache", "headache", "avalanche", "moustache", "mustache", "quiche", "psyche", "creche", "cliche",
+    "use", "abuse", "excuse", "fuse", "refuse", "muse", "ruse", "accuse", "amuse", "confuse", "recluse", "misuse",
+    "reuse", "peruse", "diffuse", "infuse",
+];
+
+/// Nouns ending in "s" (besides "ss", and a consonant and "us") that take "es" in the plural
+const S_NOUNS: [&str; 7] = ["alias", "atlas", "bias", "canvas", "gas", "lens", "iris"];
+
+/// Words that can't be used as Rust identifiers as they are
+const RUST_KEYWORDS: [&str; 51] = [
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+    "trait", "true", "type", "unsafe", "use", "where", "while", "async", "await", "dyn", "abstract", "become", "box",
+    "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
+
+/// Keywords that can't be made into raw identifiers (`r#...`), so get a trailing `_` instead
+const NON_RAW_KEYWORDS: [&str; 4] = ["crate", "self", "Self", "super"];
+
+/// Registers every filter in the pack on `tera`:
+/// - `snake_case`, `kebab_case`, `pascal_case`, `camel_case`, and `screaming_snake_case`, which
+///   split a name into words (at spaces, punctuation, and changes of case) and join them again
+/// - `pluralize` and `singularize`, which change the last word of a name using the common English
+///   rules, keeping its case
+/// - `rust_ident`, which makes a string into a valid Rust identifier
+pub fn register_filters(tera: &mut Tera) {
+    tera.register_filter("snake_case", snake_case);
+    tera.register_filter("kebab_case", kebab_case);
+    tera.register_filter("pascal_case", pascal_case);
+    tera.register_filter("camel_case", camel_case);
+    tera.register_filter("screaming_snake_case", screaming_snake_case);
+    tera.register_filter("pluralize", pluralize);
+    tera.register_filter("singularize", singularize);
+    tera.register_filter("rust_ident", rust_ident);
+}
+
+pub fn snake_case(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("snake_case", "value", String, value);
+    Ok(Value::String(join_words(&name, "_", str::to_lowercase)))
+}
+
+pub fn kebab_case(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("kebab_case", "value", String, value);
+    Ok(Value::String(join_words(&name, "-", str::to_lowercase)))
+}
+
+pub fn pascal_case(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("pascal_case", "value", String, value);
+    Ok(Value::String(join_words(&name, "", capitalize)))
+}
+
+pub fn camel_case(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("camel_case", "value", String, value);
+    let pascal_name = join_words(&name, "", capitalize);
+    let mut chars = pascal_name.chars();
+    let camel_name = match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    };
+    Ok(Value::String(camel_name))
+}
+
+pub fn screaming_snake_case(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("screaming_snake_case", "value", String, value);
+    Ok(Value::String(join_words(&name, "_", str::to_uppercase)))
+}
+
+pub fn pluralize(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("pluralize", "value", String, value);
+    Ok(Value::String(change_last_word(&name, get_plural)))
+}
+
+pub fn singularize(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("singularize", "value", String, value);
+    Ok(Value::String(change_last_word(&name, get_singular)))
+}
+
+/// Replaces anything but ASCII letters, digits, and `_` with `_`, and puts a `_` in front of a
+/// leading digit. Keywords become raw identifiers (`r#type`), or get a trailing `_` if they can't be.
+/// Fails for names that would be nothing but underscores, such as an empty one.
+pub fn rust_ident(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = try_get_value!("rust_ident", "value", String, value);
+    let mut ident: String = name.trim().chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if ident.chars().all(|c| c == '_') {
+        return Err(tera::Error::msg(format!("Filter `rust_ident` can't make an identifier from `{}`, which has no letters or digits", name)));
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+
+    if NON_RAW_KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    } else if RUST_KEYWORDS.contains(&ident.as_str()) {
+        ident.insert_str(0, "r#");
+    }
+    Ok(Value::String(ident))
+}
+
+/// Splits a name into its words: at anything that isn't a letter or digit, where a lowercase
+/// letter or digit is followed by an uppercase letter, and before the last letter of a run of
+/// capitals that's followed by a lowercase letter (so `HTTPServer` is `HTTP` and `Server`).
+fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if let Some(previous) = word.chars().last() && c.is_uppercase() {
+            let next_is_lowercase = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if !previous.is_uppercase() || next_is_lowercase {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(*c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Splits a name into words, changes each word with `change_case`, and joins them with `separator`.
+fn join_words(name: &str, separator: &str, change_case: fn(&str) -> String) -> String {
+    split_words(name).iter().map(|word| change_case(word)).collect::<Vec<_>>().join(separator)
+}
+
+/// Uppercases the first letter of a word, and lowercases the rest.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+/// Changes the last run of letters in `name` with `change_word` (which is given it in lowercase),
+/// keeping its case: all capitals, a leading capital, or all lowercase.
+fn change_last_word(name: &str, change_word: fn(&str) -> String) -> String {
+    let word_end = match name.rfind(|c: char| c.is_alphabetic()) {
+        Some(last_letter) => last_letter + name[last_letter..].chars().next().map_or(1, char::len_utf8),
+        None => return name.to_string(),
+    };
+    let word_start = name[..word_end].rfind(|c: char| !c.is_alphabetic()).map_or(0, |i| i + name[i..].chars().next().map_or(1, char::len_utf8));
+
+    // A camelCase or PascalCase name has its last word after the last capital
+    let word_start = name[word_start..word_end].rfind(|c: char| c.is_uppercase())
+        .filter(|_| name[word_start..word_end].chars().any(|c| c.is_lowercase()))
+        .map_or(word_start, |i| word_start + i);
+
+    let word = &name[word_start..word_end];
+    let changed_word = change_word(&word.to_lowercase());
+    let changed_word = if word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase()) {
+        changed_word.to_uppercase()
+    } else if word.starts_with(|c: char| c.is_uppercase()) {
+        capitalize(&changed_word)
+    } else {
+        changed_word
+    };
+
+    format!("{}{}{}", &name[..word_start], changed_word, &name[word_end..])
+}
+
+/// Returns the plural of a lowercase English noun.
+fn get_plural(word: &str) -> String {
+    if UNCOUNTABLE_NOUNS.contains(&word) || IRREGULAR_NOUNS.iter().any(|(_, plural)| *plural == word) {
+        return word.to_string();
+    }
+    if let Some((_, plural)) = IRREGULAR_NOUNS.iter().find(|(singular, _)| *singular == word) {
+        return plural.to_string();
+    }
+
+    let ends_with_consonant_y = word.ends_with('y') && word.len() > 1 && !word[..word.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u']);
+    if ends_with_consonant_y {
+        format!("{}ies", &word[..word.len() - 1])
+    } else if ["s", "x", "z", "ch", "sh"].iter().any(|ending| word.ends_with(ending)) {
+        format!("{}es", word)
+    } else {
+        format!("{}s", word)
+    }
+}
+
+/// Returns the singular of a lowercase English noun.
+fn get_singular(word: &str) -> String {
+    if UNCOUNTABLE_NOUNS.contains(&word) || IRREGULAR_NOUNS.iter().any(|(singular, _)| *singular == word) {
+        return word.to_string();
+    }
+    if let Some((singular, _)) = IRREGULAR_NOUNS.iter().find(|(_, plural)| *plural == word) {
+        return singular.to_string();
+    }
+
+    if let Some(stem) = word.strip_suffix("ies") {
+        let ie_noun = format!("{}ie", stem);
+        return if IE_NOUNS.contains(&ie_noun.as_str()) { ie_noun } else { format!("{}y", stem) };
+    }
+
+    // Nouns that take "es" end in a sibilant, but so do plenty of nouns ending in "e" that just take "s"
+    if let Some(stem) = word.strip_suffix("es") && !E_NOUNS.contains(&&word[..word.len() - 1]) {
+        let takes_es = ["ss", "x", "zz", "ch", "sh"].iter().any(|ending| stem.ends_with(ending))
+            || S_NOUNS.contains(&stem)
+            || ends_with_consonant_us(stem);
+        if takes_es {
+            return stem.to_string();
+        }
+    }
+
+    if word.ends_with('s') && !word.ends_with("ss") && !word.ends_with("is") && !ends_with_consonant_us(word) {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+/// Returns whether `word` ends in a consonant and "us", like "status" or "bus", rather than like
+/// "house" or "cause" once their "e" is taken off.
+fn ends_with_consonant_us(word: &str) -> bool {
+    word.strip_suffix("us").and_then(|stem| stem.chars().last()).is_some_and(|c| !"aeiou".contains(c))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tera::Context;
+
+    fn render(template: &str) -> String {
+        let mut tera = Tera::default();
+        register_filters(&mut tera);
+        tera.add_raw_template("test", template).unwrap();
+        tera.render("test", &Context::new()).unwrap()
+    }
+
+    #[test]
+    pub fn case_filters_split_names_into_words() {
+        assert_eq!(render(r#"{{ "HTTPServer error-code" | snake_case }}"#), "http_server_error_code");
+        assert_eq!(render(r#"{{ "userAccount2Id" | kebab_case }}"#), "user-account2-id");
+        assert_eq!(render(r#"{{ "my_crate name" | pascal_case }}"#), "MyCrateName");
+        assert_eq!(render(r#"{{ "My Crate-name" | camel_case }}"#), "myCrateName");
+        assert_eq!(render(r#"{{ "maxRetryCount" | screaming_snake_case }}"#), "MAX_RETRY_COUNT");
+        assert_eq!(render(r#"{{ "" | snake_case }}"#), "");
+    }
+
+    #[test]
+    pub fn inflection_filters_change_the_last_word() {
+        assert_eq!(render(r#"{{ "user_account" | pluralize }}"#), "user_accounts");
+        assert_eq!(render(r#"{{ "Category" | pluralize }} {{ "box" | pluralize }} {{ "key" | pluralize }} {{ "knife" | pluralize }}"#), "Categories boxes keys knives");
+        assert_eq!(render(r#"{{ "BlogPerson" | pluralize }} {{ "SHEEP" | pluralize }} {{ "USER" | pluralize }}"#), "BlogPeople SHEEP USERS");
+        assert_eq!(render(r#"{{ "user_accounts" | singularize }}"#), "user_account");
+        assert_eq!(render(r#"{{ "Categories" | singularize }} {{ "boxes" | singularize }} {{ "status" | singularize }} {{ "wolves" | singularize }}"#), "Category box status wolf");
+        assert_eq!(render(r#"{{ "children" | singularize }} {{ "42" | singularize }}"#), "child 42");
+    }
+
+    #[test]
+    pub fn singularize_undoes_pluralize() {
+        let words = [
+            "user", "status", "alias", "archive", "valve", "cache", "movie", "category", "box", "class", "bus", "knife",
+            "wolf", "leaf", "child", "person", "sheep", "key", "church", "dish", "size", "house", "cause", "database",
+            "case", "response", "license", "use", "excuse", "focus", "virus", "quiz", "buzz", "hero", "cafe", "gulf",
+            "niche", "cookie", "day", "gas", "analysis",
+        ];
+        for word in words {
+            let plural = get_plural(word);
+            assert_eq!(get_singular(&plural), word, "{} -> {}", word, plural);
+        }
+
+        assert_eq!(get_plural("status"), "statuses");
+        assert_eq!(get_plural("cafe"), "cafes");
+        assert_eq!(get_singular("caches"), "cache");
+        assert_eq!(get_singular("movies"), "movie");
+        assert_eq!(get_singular("bus"), "bus");
+    }
+
+    #[test]
+    pub fn rust_ident_makes_valid_identifiers() {
+        assert_eq!(render(r#"{{ "my-crate" | rust_ident }}"#), "my_crate");
+        assert_eq!(render(r#"{{ "2fast" | rust_ident }}"#), "_2fast");
+        assert_eq!(render(r#"{{ "x²½" | rust_ident }} {{ "٣x" | rust_ident }} {{ "café" | rust_ident }}"#), "x__ _x caf_");
+        assert_eq!(render(r#"{{ "type" | rust_ident }} {{ "self" | rust_ident }} {{ "_id" | rust_ident }}"#), "r#type self_ _id");
+        for name in ["", "_", " - "] {
+            assert!(rust_ident(&Value::from(name), &HashMap::new()).is_err());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use tera::{Context, Filter, Value};
+use crate::template_rendering::new_tera;
+
+// Run the shell commands a template asks for before and after it's rendered
+
+/// The name a hook command is given when it's rendered
+const HOOK_TEMPLATE_NAME: &str = "__utsusu_hook";
+
+#[derive(Debug)]
+pub enum HookError {
+    /// Rendering a hook command failed: (raw command, error)
+    CommandRenderError(String, tera::Error),
+    /// A hook command could not be started: (command, error)
+    SpawnError(String, std::io::Error),
+    /// A hook command exited unsuccessfully: (command, exit code, if it exited normally)
+    CommandFailed(String, Option<i32>),
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::CommandRenderError(command, tera_error) => write!(f, "could not render command '{}': {}", command, tera_error),
+            HookError::SpawnError(command, io_error) => write!(f, "could not run '{}': {}", command, io_error),
+            HookError::CommandFailed(command, Some(code)) => write!(f, "'{}' exited with status {}", command, code),
+            HookError::CommandFailed(command, None) => write!(f, "'{}' was terminated by a signal", command),
+        }
+    }
+}
+
+/// Renders each of `commands` against `context` and runs them in order through the shell, with
+/// `working_directory` as the current directory. The commands share this process's standard input
+/// and output.
+///
+/// Every value a command substitutes (`{{ ... }}`) is quoted for the shell with [shell_quote], so a
+/// variable can't inject shell code; a value that's meant to be shell code has to be marked with
+/// Tera's `safe` filter. The `shell_quote` filter is also available, for quoting parts of a
+/// command that are marked safe.
+///
+/// Stops at the first command that fails.
+pub fn run_hooks<P: AsRef<Path>>(commands: &[String], context: &Context, working_directory: &P) -> Result<(), HookError> {
+    for raw_command in commands {
+        let command = match render_command(raw_command, context) {
+            Err(tera_error) => return Err(HookError::CommandRenderError(raw_command.clone(), tera_error)),
+            Ok(command) => command,
+        };
+
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+
+        match shell.arg(&command).current_dir(working_directory).status() {
+            Err(spawn_error) => return Err(HookError::SpawnError(command, spawn_error)),
+            Ok(status) if !status.success() => return Err(HookError::CommandFailed(command, status.code())),
+            Ok(_) => {},
+        };
+    }
+
+    Ok(())
+}
+
+/// Renders a hook command, quoting each value it substitutes for the shell.
+fn render_command(raw_command: &str, context: &Context) -> tera::Result<String> {
+    let mut tera = new_tera();
+    tera.register_filter("shell_quote", ShellQuoteFilter);
+    tera.set_escape_fn(shell_quote);
+    tera.autoescape_on(vec![HOOK_TEMPLATE_NAME]);
+    tera.add_raw_template(HOOK_TEMPLATE_NAME, raw_command)?;
+    tera.render(HOOK_TEMPLATE_NAME, context)
+}
+
+/// Quotes `value` so the shell hooks are run with treats it as a single word, with no expansions.
+/// Values made up only of characters the shell doesn't treat specially are left as they are.
+pub fn shell_quote(value: &str) -> String {
+    let is_plain = !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:=@,+%".contains(c));
+    if cfg!(windows) {
+        if is_plain && !value.contains('%') {
+            value.to_string()
+        } else {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+    } else if is_plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// The `shell_quote` filter, whose output is already quoted, so it isn't quoted again.
+struct ShellQuoteFilter;
+
+impl Filter for ShellQuoteFilter {
+    fn filter(&self, value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+        let quoted = match value {
+            Value::String(string_value) => shell_quote(string_value),
+            other => shell_quote(&other.to_string()),
+        };
+        Ok(Value::String(quoted))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use crate::test_utils::TestDirectory;
+
+    #[test]
+    pub fn run_hooks_renders_and_runs_commands() {
+        let working_directory = TestDirectory::new("hooks");
+
+        let mut context = Context::new();
+        context.insert("crate_name", "demo");
+
+        let commands = vec!["echo {{ crate_name }} > name.txt".to_string(), "exit 3".to_string(), "touch never.txt".to_string()];
+        let result = run_hooks(&commands, &context, &working_directory.get_path());
+        assert!(matches!(result, Err(HookError::CommandFailed(command, Some(3))) if command == "exit 3"));
+        assert_eq!(std::fs::read_to_string(working_directory.join("name.txt")).unwrap(), "demo\n");
+        assert!(!working_directory.join("never.txt").exists());
+    }
+
+    #[test]
+    pub fn run_hooks_quotes_substituted_values() {
+        let working_directory = TestDirectory::new("hooks-quoting");
+
+        let mut context = Context::new();
+        context.insert("crate_name", "x; touch pwned.txt");
+        context.insert("quote", "it's $HOME");
+        context.insert("script", "echo raw > raw.txt");
+
+        let commands = vec![
+            "echo {{ crate_name }} > name.txt".to_string(),
+            "echo {{ quote }} > quote.txt".to_string(),
+            "{{ script | safe }}".to_string(),
+            "echo {{ script | safe | shell_quote }} > script.txt".to_string(),
+        ];
+        run_hooks(&commands, &context, &working_directory.get_path()).unwrap();
+        assert_eq!(std::fs::read_to_string(working_directory.join("name.txt")).unwrap(), "x; touch pwned.txt\n");
+        assert!(!working_directory.join("pwned.txt").exists());
+        assert_eq!(std::fs::read_to_string(working_directory.join("quote.txt")).unwrap(), "it's $HOME\n");
+        assert_eq!(std::fs::read_to_string(working_directory.join("raw.txt")).unwrap(), "raw\n");
+        assert_eq!(std::fs::read_to_string(working_directory.join("script.txt")).unwrap(), "echo raw > raw.txt\n");
+
+        assert_eq!(shell_quote("demo-1.0"), "demo-1.0");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b'c"), "'a b'\\''c'");
+    }
+}
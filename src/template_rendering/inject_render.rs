@@ -0,0 +1,169 @@
+use std::fmt;
+use crate::template_config::{InjectPosition, Injection};
+
+// Take a rendered snippet and put it into an existing file
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InjectError {
+    /// No line in the target file matches the anchor: (anchor regex)
+    AnchorNotFound(String),
+    /// No line after the anchor line matches the end anchor: (end anchor regex)
+    EndAnchorNotFound(String),
+}
+
+impl fmt::Display for InjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InjectError::AnchorNotFound(anchor) => write!(f, "no line matches the anchor '{}'", anchor),
+            InjectError::EndAnchorNotFound(end_anchor) => write!(f, "no line after the anchor matches the end anchor '{}'", end_anchor),
+        }
+    }
+}
+
+/// Puts `snippet` into `existing` (the contents of the target file) as described by `injection`.
+///
+/// This is idempotent: for [InjectPosition::Before] and [InjectPosition::After], nothing is added if
+/// the snippet's lines are already next to the anchor, and [InjectPosition::ReplaceBetween] always leaves
+/// the same result. Returns None if the contents are unchanged.
+pub fn inject_snippet(existing: &str, snippet: &str, injection: &Injection) -> Result<Option<String>, InjectError> {
+    // The snippet is inserted as whole lines
+    let mut snippet = snippet.to_string();
+    if !snippet.is_empty() && !snippet.ends_with('\n') {
+        snippet.push('\n');
+    }
+
+    let lines: Vec<&str> = existing.split_inclusive('\n').collect();
+    let anchor_index = match lines.iter().position(|line| injection.get_anchor().is_match(line.trim_end_matches(['\r', '\n']))) {
+        Some(anchor_index) => anchor_index,
+        None => return Err(InjectError::AnchorNotFound(injection.get_anchor().as_str().to_string())),
+    };
+
+    // -- Work out which lines the snippet replaces
+    let (start, end) = match injection.get_position() {
+        InjectPosition::Before | InjectPosition::After => {
+            // Earlier injections pile up next to the anchor, so the snippet counts as already there if
+            // it's anywhere in the block of lines between the anchor and the nearest blank line. The
+            // snippet can have blank lines of its own, so the block always reaches at least as many
+            // lines past the anchor as the snippet has before a blank line ends it.
+            let snippet_length = snippet.lines().count();
+            let (insert_index, block) = match injection.get_position() {
+                InjectPosition::Before => {
+                    let window_start = anchor_index.saturating_sub(snippet_length);
+                    let block_start = lines[..window_start].iter().rposition(|line| line.trim().is_empty()).map_or(0, |i| i + 1);
+                    (anchor_index, &lines[block_start..anchor_index])
+                },
+                _ => {
+                    let window_end = (anchor_index + 1 + snippet_length).min(lines.len());
+                    let block_end = lines[window_end..].iter().position(|line| line.trim().is_empty()).map_or(lines.len(), |i| window_end + i);
+                    (anchor_index + 1, &lines[anchor_index + 1..block_end])
+                },
+            };
+            if !snippet.trim().is_empty() && contains_lines(block, &snippet) {
+                return Ok(None);
+            }
+
+            (insert_index, insert_index)
+        },
+        InjectPosition::ReplaceBetween => {
+            let end_anchor = injection.get_end_anchor().unwrap_or(injection.get_anchor());
+            match lines[anchor_index + 1..].iter().position(|line| end_anchor.is_match(line.trim_end_matches(['\r', '\n']))) {
+                Some(end_offset) => (anchor_index + 1, anchor_index + 1 + end_offset),
+                None => return Err(InjectError::EndAnchorNotFound(end_anchor.as_str().to_string())),
+            }
+        },
+    };
+
+    // -- Splice it in
+    let mut injected = String::with_capacity(existing.len() + snippet.len());
+    for line in &lines[..start] {
+        injected.push_str(line);
+    }
+    if start > 0 && !injected.ends_with('\n') {
+        injected.push('\n');
+    }
+    injected.push_str(&snippet);
+    for line in &lines[end..] {
+        injected.push_str(line);
+    }
+
+    if injected == existing {
+        Ok(None)
+    } else {
+        Ok(Some(injected))
+    }
+}
+
+/// Returns whether the lines of `snippet` appear, as whole lines and in order, somewhere in `block`.
+fn contains_lines(block: &[&str], snippet: &str) -> bool {
+    let snippet_lines: Vec<&str> = snippet.lines().map(str::trim_end).collect();
+    block.windows(snippet_lines.len()).any(|window| {
+        window.iter().zip(&snippet_lines).all(|(line, snippet_line)| line.trim_end() == *snippet_line)
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use regex::Regex;
+
+    const EXISTING: &str = "mod a;\n// modules\nmod z;\n\n// begin routes\nold\n// end routes\n";
+
+    #[test]
+    pub fn inject_snippet_inserts_before_and_after_once() {
+        let after = Injection::new(Regex::new("^// modules$").unwrap(), InjectPosition::After);
+        let injected = inject_snippet(EXISTING, "mod b;", &after).unwrap().unwrap();
+        assert_eq!(injected, "mod a;\n// modules\nmod b;\nmod z;\n\n// begin routes\nold\n// end routes\n");
+        assert_eq!(inject_snippet(&injected, "mod b;", &after).unwrap(), None);
+
+        let before = Injection::new(Regex::new("modules").unwrap(), InjectPosition::Before);
+        let injected = inject_snippet(EXISTING, "mod b;\n", &before).unwrap().unwrap();
+        assert_eq!(injected, "mod a;\nmod b;\n// modules\nmod z;\n\n// begin routes\nold\n// end routes\n");
+
+        // Only whole lines next to the anchor count as the snippet already being there
+        let existing = "pub mod b;\n// modules\nmod z;\n";
+        let injected = inject_snippet(existing, "mod b;", &after).unwrap().unwrap();
+        assert_eq!(injected, "pub mod b;\n// modules\nmod b;\nmod z;\n");
+        assert_eq!(inject_snippet(&injected, "mod b;", &after).unwrap(), None);
+        assert_eq!(inject_snippet(&injected, "mod c;", &after).unwrap().unwrap(), "pub mod b;\n// modules\nmod c;\nmod b;\nmod z;\n");
+
+        let existing = "use routes;\n\n// modules\nmod z;\n";
+        let injected = inject_snippet(existing, "routes", &after).unwrap().unwrap();
+        assert_eq!(injected, "use routes;\n\n// modules\nroutes\nmod z;\n");
+        let existing = "mod b;\n\nmod a;\n// modules\n";
+        assert_eq!(inject_snippet(existing, "mod b;", &before).unwrap().unwrap(), "mod b;\n\nmod a;\nmod b;\n// modules\n");
+        assert_eq!(inject_snippet(existing, "mod a;", &before).unwrap(), None);
+
+        let missing = Injection::new(Regex::new("^// nowhere$").unwrap(), InjectPosition::After);
+        assert_eq!(inject_snippet(EXISTING, "mod b;", &missing), Err(InjectError::AnchorNotFound("^// nowhere$".to_string())));
+    }
+
+    #[test]
+    pub fn inject_snippet_finds_snippets_with_blank_lines() {
+        let snippet = "/// Routes\nmod routes;\n\n/// Models\nmod models;\n";
+
+        let after = Injection::new(Regex::new("^// modules$").unwrap(), InjectPosition::After);
+        let injected = inject_snippet(EXISTING, snippet, &after).unwrap().unwrap();
+        assert_eq!(injected, "mod a;\n// modules\n/// Routes\nmod routes;\n\n/// Models\nmod models;\nmod z;\n\n// begin routes\nold\n// end routes\n");
+        assert_eq!(inject_snippet(&injected, snippet, &after).unwrap(), None);
+        let injected = inject_snippet(&injected, "mod b;", &after).unwrap().unwrap();
+        assert_eq!(inject_snippet(&injected, snippet, &after).unwrap(), None);
+
+        let before = Injection::new(Regex::new("^// modules$").unwrap(), InjectPosition::Before);
+        let injected = inject_snippet(EXISTING, snippet, &before).unwrap().unwrap();
+        assert_eq!(injected, "mod a;\n/// Routes\nmod routes;\n\n/// Models\nmod models;\n// modules\nmod z;\n\n// begin routes\nold\n// end routes\n");
+        assert_eq!(inject_snippet(&injected, snippet, &before).unwrap(), None);
+        let injected = inject_snippet(&injected, "mod b;", &before).unwrap().unwrap();
+        assert_eq!(inject_snippet(&injected, snippet, &before).unwrap(), None);
+    }
+
+    #[test]
+    pub fn inject_snippet_replaces_between_anchors() {
+        let mut between = Injection::new(Regex::new("begin routes").unwrap(), InjectPosition::ReplaceBetween);
+        between.set_end_anchor(Some(Regex::new("end routes").unwrap()));
+
+        let injected = inject_snippet(EXISTING, "new\nnewer\n", &between).unwrap().unwrap();
+        assert_eq!(injected, "mod a;\n// modules\nmod z;\n\n// begin routes\nnew\nnewer\n// end routes\n");
+        assert_eq!(inject_snippet(&injected, "new\nnewer\n", &between).unwrap(), None);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::path::Path;
+use saphyr::{LoadableYamlNode, YamlEmitter, YamlOwned};
+use tera::Value;
+use crate::template_config::ArrayMergeStrategy;
+use crate::value_conversion::{value_to_toml, value_to_yaml, yaml_to_value};
+
+// Merge a rendered YAML, TOML, or JSON document into an existing file
+
+/// The structured formats rendered files can be merged into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl MergeFormat {
+    /// Works out the format of a file from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: &P) -> Option<Self> {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some("yml") | Some("yaml") => Some(MergeFormat::Yaml),
+            Some("toml") => Some(MergeFormat::Toml),
+            Some("json") => Some(MergeFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeError {
+    /// The file isn't YAML, TOML, or JSON, going by its extension: (file)
+    UnsupportedFormat(String),
+    /// The existing file could not be parsed: (error message)
+    ExistingParseError(String),
+    /// The rendered file could not be parsed: (error message)
+    RenderedParseError(String),
+    /// The merged document could not be written out in the file's format: (error message)
+    SerializeError(String),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::UnsupportedFormat(file) => write!(f, "'{}' is not a YAML, TOML, or JSON file", file),
+            MergeError::ExistingParseError(message) => write!(f, "could not parse the existing file: {}", message),
+            MergeError::RenderedParseError(message) => write!(f, "could not parse the rendered file: {}", message),
+            MergeError::SerializeError(message) => write!(f, "could not write the merged file: {}", message),
+        }
+    }
+}
+
+/// Deep-merges the `rendered` document into the `existing` contents of the file at `path`, whose
+/// extension decides the format.
+///
+/// Mappings are merged key by key, arrays are combined according to `array_strategy`, and anything
+/// else in the rendered document replaces what's in the existing one. The merged document is
+/// written out afresh, so comments and formatting in the existing file are not kept. Returns None
+/// if the merged document is the same as the existing one.
+pub fn merge_documents<P: AsRef<Path>>(path: &P, existing: &str, rendered: &str, array_strategy: ArrayMergeStrategy) -> Result<Option<String>, MergeError> {
+    let format = match MergeFormat::from_path(path) {
+        Some(format) => format,
+        None => return Err(MergeError::UnsupportedFormat(path.as_ref().display().to_string())),
+    };
+
+    let existing_document = parse_document(existing, format).map_err(MergeError::ExistingParseError)?;
+    let rendered_document = parse_document(rendered, format).map_err(MergeError::RenderedParseError)?;
+
+    let mut merged_document = existing_document.clone();
+    merge_values(&mut merged_document, rendered_document, array_strategy);
+    if merged_document == existing_document {
+        return Ok(None);
+    }
+
+    write_document(&merged_document, format).map(Some)
+}
+
+/// Merges `overlay` into `base`: objects are merged key by key, arrays are combined according to
+/// `array_strategy`, and otherwise `overlay` replaces `base`.
+pub fn merge_values(base: &mut Value, overlay: Value, array_strategy: ArrayMergeStrategy) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, overlay_value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(base_value) => merge_values(base_value, overlay_value, array_strategy),
+                    None => {
+                        base_map.insert(key, overlay_value);
+                    },
+                };
+            }
+        },
+        (Value::Array(base_items), Value::Array(overlay_items)) => match array_strategy {
+            ArrayMergeStrategy::Replace => *base_items = overlay_items,
+            ArrayMergeStrategy::Append => base_items.extend(overlay_items),
+            ArrayMergeStrategy::Union => {
+                for item in overlay_items {
+                    if !base_items.contains(&item) {
+                        base_items.push(item);
+                    }
+                }
+            },
+        },
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Parses a document in the given format. An empty document is null.
+fn parse_document(contents: &str, format: MergeFormat) -> Result<Value, String> {
+    if contents.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+
+    match format {
+        MergeFormat::Yaml => match YamlOwned::load_from_str(contents) {
+            Err(scan_error) => Err(scan_error.to_string()),
+            Ok(docs) => Ok(docs.first().map(yaml_to_value).unwrap_or(Value::Null)),
+        },
+        MergeFormat::Toml => match toml::from_str::<toml::Table>(contents) {
+            Err(toml_error) => Err(toml_error.to_string()),
+            Ok(table) => serde_json::to_value(table).map_err(|json_error| json_error.to_string()),
+        },
+        MergeFormat::Json => serde_json::from_str(contents).map_err(|json_error| json_error.to_string()),
+    }
+}
+
+/// Writes out a document in the given format, ending in a newline.
+fn write_document(document: &Value, format: MergeFormat) -> Result<String, MergeError> {
+    let mut contents = match format {
+        MergeFormat::Yaml => {
+            let mut emitted = String::new();
+            let mut emitter = YamlEmitter::new(&mut emitted);
+            emitter.multiline_strings(true);
+            if let Err(emit_error) = emitter.dump(&value_to_yaml(document)) {
+                return Err(MergeError::SerializeError(emit_error.to_string()));
+            }
+            emitted.strip_prefix("---\n").map(|body| body.to_string()).unwrap_or(emitted)
+        },
+        MergeFormat::Toml => match value_to_toml(document) {
+            Some(toml::Value::Table(table)) => match toml::to_string(&table) {
+                Err(toml_error) => return Err(MergeError::SerializeError(toml_error.to_string())),
+                Ok(contents) => contents,
+            },
+            _ => return Err(MergeError::SerializeError("a TOML document must be a table".to_string())),
+        },
+        MergeFormat::Json => match serde_json::to_string_pretty(document) {
+            Err(json_error) => return Err(MergeError::SerializeError(json_error.to_string())),
+            Ok(contents) => contents,
+        },
+    };
+
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    Ok(contents)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn merge_documents_deep_merges_each_format() {
+        let existing_toml = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nregex = \"1\"\n";
+        let rendered_toml = "[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\n";
+        let merged_toml = merge_documents(&"Cargo.toml", existing_toml, rendered_toml, ArrayMergeStrategy::Replace).unwrap().unwrap();
+        let merged_table: toml::Table = toml::from_str(&merged_toml).unwrap();
+        assert_eq!(merged_table["package"]["name"].as_str(), Some("demo"));
+        assert_eq!(merged_table["dependencies"]["regex"].as_str(), Some("1"));
+        assert_eq!(merged_table["dependencies"]["serde"]["features"][0].as_str(), Some("derive"));
+        assert!(merged_toml.find("[package]").unwrap() < merged_toml.find("[dependencies]").unwrap());
+        assert_eq!(merge_documents(&"Cargo.toml", &merged_toml, rendered_toml, ArrayMergeStrategy::Replace).unwrap(), None);
+
+        let existing_yaml = "services:\n  web:\n    image: nginx\n    ports:\n      - \"80:80\"\n";
+        let rendered_yaml = "services:\n  web:\n    ports:\n      - \"80:80\"\n      - \"443:443\"\n  db:\n    image: postgres\n";
+        let merged_yaml = merge_documents(&"docker-compose.yml", existing_yaml, rendered_yaml, ArrayMergeStrategy::Union).unwrap().unwrap();
+        assert_eq!(parse_document(&merged_yaml, MergeFormat::Yaml).unwrap(), serde_json::json!({
+            "services": {
+                "web": {"image": "nginx", "ports": ["80:80", "443:443"]},
+                "db": {"image": "postgres"},
+            },
+        }));
+
+        let merged_json = merge_documents(&"package.json", "{\"keywords\": [\"a\"], \"private\": true}", "{\"keywords\": [\"a\", \"b\"]}", ArrayMergeStrategy::Append).unwrap().unwrap();
+        assert_eq!(parse_document(&merged_json, MergeFormat::Json).unwrap(), serde_json::json!({"keywords": ["a", "a", "b"], "private": true}));
+
+        assert!(matches!(merge_documents(&"README.md", "", "", ArrayMergeStrategy::Replace), Err(MergeError::UnsupportedFormat(_))));
+        assert!(matches!(merge_documents(&"config.json", "{", "{}", ArrayMergeStrategy::Replace), Err(MergeError::ExistingParseError(_))));
+    }
+
+    #[test]
+    pub fn merge_documents_keeps_toml_datetimes() {
+        let merged = merge_documents(&"release.toml", "released = 1979-05-27T07:32:00Z\n", "name = \"demo\"\n", ArrayMergeStrategy::Replace).unwrap().unwrap();
+        let merged_table: toml::Table = toml::from_str(&merged).unwrap();
+        assert!(merged_table["released"].is_datetime());
+    }
+}
@@ -1,8 +1,22 @@
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use tera::Tera;
+use crate::template_config::TemplateConfig;
+use crate::template_variable::is_template_string;
 
 pub mod single_file_render;
-mod directory_render;
+pub mod directory_render;
+pub mod hooks;
+pub mod inject_render;
+pub mod merge_render;
+pub mod diagnostics;
+pub mod filters;
+
+/// The name the template is given when rendering a single template string
+const ONE_OFF_TEMPLATE_NAME: &str = "__tera_one_off";
+
+/// How many bytes from the start of a file are checked to tell whether it's binary
+pub const BINARY_CHECK_LENGTH: usize = 8 * 1024;
 
 pub fn get_all_template_filenames_from_directory<P: AsRef<Path>>(dir: &P) -> std::io::Result<Vec<PathBuf>> {
     let mut filenames = Vec::new();
@@ -29,10 +43,69 @@ pub fn get_all_template_filenames_from_directory<P: AsRef<Path>>(dir: &P) -> std
     Ok(filenames)
 }
 
-pub fn load_template_files_from_filenames<P: AsRef<Path>>(files: &[P]) -> tera::Result<Tera> {
+/// Returns every file under `dir` that `config` includes, without descending into directories that
+/// `config` excludes.
+pub fn get_included_template_filenames_from_directory<P: AsRef<Path>>(dir: &P, config: &TemplateConfig) -> std::io::Result<Vec<PathBuf>> {
+    let root = dir.as_ref();
+    let mut filenames = Vec::new();
+
+    let mut dirs_to_traverse: Vec<PathBuf> = vec![root.to_path_buf()];
+
+    while let Some(traverse_dir) = dirs_to_traverse.pop() {
+        for entry in std::fs::read_dir(traverse_dir)? {
+            let path = entry?.path();
+            let relative_path = path.strip_prefix(root).unwrap_or(&path);
+            if path.is_dir() {
+                if config.should_traverse_directory(&relative_path) {
+                    dirs_to_traverse.push(path);
+                }
+            } else if config.should_include_file(&relative_path) {
+                filenames.push(path);
+            }
+        }
+    }
+
+    Ok(filenames)
+}
+
+/// Returns whether the file at `path` looks like a binary file rather than text, i.e. whether its
+/// first [BINARY_CHECK_LENGTH] bytes aren't valid UTF-8 or contain a NUL byte.
+pub fn is_binary_file<P: AsRef<Path>>(path: &P) -> std::io::Result<bool> {
+    let mut bytes = Vec::with_capacity(BINARY_CHECK_LENGTH);
+    std::fs::File::open(path)?.take(BINARY_CHECK_LENGTH as u64).read_to_end(&mut bytes)?;
+    if bytes.contains(&0) {
+        return Ok(true);
+    }
+
+    // A character can be cut off at the end of what was read, which only counts if the file ends there
+    match std::str::from_utf8(&bytes) {
+        Ok(_) => Ok(false),
+        Err(utf8_error) => Ok(utf8_error.error_len().is_some() || bytes.len() < BINARY_CHECK_LENGTH),
+    }
+}
+
+/// Returns a new Tera instance with the built-in filters registered.
+pub fn new_tera() -> Tera {
     let mut tera = Tera::default();
+    filters::register_filters(&mut tera);
+    tera
+}
+
+/// Renders a single template string against a context, like `Tera::one_off`, but with the built-in
+/// filters available.
+pub fn render_one_off(input: &str, context: &tera::Context, autoescape: bool) -> tera::Result<String> {
+    let mut tera = new_tera();
+    if autoescape {
+        tera.autoescape_on(vec![ONE_OFF_TEMPLATE_NAME]);
+    }
+    tera.add_raw_template(ONE_OFF_TEMPLATE_NAME, input)?;
+    tera.render(ONE_OFF_TEMPLATE_NAME, context)
+}
 
-    let _ = tera.add_template_files(
+pub fn load_template_files_from_filenames<P: AsRef<Path>>(files: &[P]) -> tera::Result<Tera> {
+    let mut tera = new_tera();
+
+    tera.add_template_files(
         files
             .iter()
             .filter(|p| {
@@ -45,3 +118,90 @@ pub fn load_template_files_from_filenames<P: AsRef<Path>>(files: &[P]) -> tera::
     Ok(tera)
 }
 
+/// Loads each of the (file, template name) pairs into a new Tera instance, under the given name.
+pub fn load_named_template_files<P: AsRef<Path>>(files: &[(P, String)]) -> tera::Result<Tera> {
+    let mut tera = new_tera();
+
+    tera.add_template_files(files.iter().map(|(p, name)| (p, Some(name.as_str()))))?;
+
+    Ok(tera)
+}
+
+
+/// Renders a path that may contain template variables, such as an output filename or directory.
+/// Values are substituted as they are, without HTML escaping.
+pub fn render_path(raw_path: &str, context: &tera::Context) -> tera::Result<PathBuf> {
+    render_one_off(raw_path, context, false).map(PathBuf::from)
+}
+
+/// Evaluates a condition, such as the `when` of a variable, against a context.
+///
+/// A condition is either a bare Tera expression (`use_ci and ci_provider == "github"`), or a
+/// template (`{{ use_ci }}`) whose rendered output is considered false if it is empty or one of
+/// `false`, `no`, `n`, `off`, or `0` (ignoring case and surrounding whitespace), and true otherwise.
+pub fn evaluate_condition(condition: &str, context: &tera::Context) -> tera::Result<bool> {
+    if is_template_string(condition) {
+        let rendered = render_one_off(condition, context, false)?;
+        let rendered = rendered.trim().to_lowercase();
+        Ok(!matches!(rendered.as_str(), "" | "false" | "no" | "n" | "off" | "0"))
+    } else {
+        let rendered = render_one_off(&format!("{{% if {} %}}true{{% endif %}}", condition), context, false)?;
+        Ok(rendered == "true")
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TestDirectory;
+
+    #[test]
+    pub fn evaluate_condition_works() {
+        let mut context = tera::Context::new();
+        context.insert("use_ci", &true);
+        context.insert("use_docker", &false);
+        context.insert("ci_provider", "github");
+
+        assert!(evaluate_condition("use_ci", &context).unwrap());
+        assert!(!evaluate_condition("use_docker", &context).unwrap());
+        assert!(evaluate_condition(r#"use_ci and ci_provider == "github""#, &context).unwrap());
+        assert!(!evaluate_condition("not use_ci", &context).unwrap());
+        assert!(evaluate_condition("{{ use_ci }}", &context).unwrap());
+        assert!(!evaluate_condition("{{ use_docker }}", &context).unwrap());
+        assert!(!evaluate_condition("{% if ci_provider == 'gitlab' %}yes{% endif %}", &context).unwrap());
+        assert!(!evaluate_condition("undefined_variable", &context).unwrap());
+        assert!(evaluate_condition("use_ci ==", &context).is_err());
+        assert!(evaluate_condition(r#"ci_provider | pascal_case == "Github""#, &context).unwrap());
+    }
+
+    #[test]
+    pub fn render_path_can_use_the_built_in_filters() {
+        let mut context = tera::Context::new();
+        context.insert("name", "HttpClient");
+        assert_eq!(render_path("src/{{ name | snake_case }}.rs", &context).unwrap(), PathBuf::from("src/http_client.rs"));
+        assert_eq!(render_path("{{ name | pluralize | kebab_case }}/mod.rs", &context).unwrap(), PathBuf::from("http-clients/mod.rs"));
+    }
+
+    #[test]
+    pub fn render_path_does_not_escape_values() {
+        let mut context = tera::Context::new();
+        context.insert("module", "net/tcp & udp");
+        context.insert("owner", "o'brien");
+        assert_eq!(render_path("src/{{ module }}/{{ owner }}.rs", &context).unwrap(), PathBuf::from("src/net/tcp & udp/o'brien.rs"));
+    }
+
+    #[test]
+    pub fn is_binary_file_checks_the_start_of_the_file() {
+        let files = TestDirectory::new("binary-files");
+        assert!(!is_binary_file(&files.write("plain.txt", "plain text")).unwrap());
+        assert!(is_binary_file(&files.write("nul.bin", b"text\0more")).unwrap());
+        assert!(is_binary_file(&files.write("latin1.txt", b"caf\xe9")).unwrap());
+        assert!(is_binary_file(&files.write("truncated.txt", &"café".as_bytes()[..4])).unwrap());
+
+        // A character cut off by the end of the check doesn't make the file binary
+        let mut long_text = "a".repeat(BINARY_CHECK_LENGTH - 1);
+        long_text.push_str("é and more");
+        assert!(!is_binary_file(&files.write("long.txt", &long_text)).unwrap());
+    }
+}
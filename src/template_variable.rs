@@ -0,0 +1,711 @@
+//! This module provides the definitions of template variables, and conversion of user input into
+//! values of their types.
+
+use std::fmt;
+use std::sync::LazyLock;
+use regex::Regex;
+use tera::Value;
+use crate::template_rendering::{evaluate_condition, render_one_off};
+
+static IDENTIFIER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap());
+static SEMVER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-((?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*))*))?(?:\+([0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*))?$").unwrap());
+static EMAIL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").unwrap());
+static URL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*://[^\s/?#]+\S*$").unwrap());
+static TEMPLATE_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)\{\{(.*?)\}\}|\{%(.*?)%\}").unwrap());
+static STRING_LITERAL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""[^"]*"|'[^']*'|`[^`]*`"#).unwrap());
+static TEMPLATE_IDENTIFIER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|(\|)\s*|[^.\w])([A-Za-z_]\w*)").unwrap());
+
+/// Words with a meaning of their own in Tera expressions and tags, which aren't variables
+const TERA_KEYWORDS: [&str; 34] = [
+    "and", "or", "not", "in", "is", "if", "elif", "else", "endif", "for", "endfor", "set", "set_global", "filter",
+    "endfilter", "block", "endblock", "macro", "endmacro", "include", "import", "extends", "raw", "endraw", "true",
+    "false", "True", "False", "as", "with", "loop", "break", "continue", "ignore",
+];
+
+/// The type of a template variable. This is determined by the YAML type of its default value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariableType {
+    /// A string. Variables without a default value are also treated as strings.
+    String,
+
+    /// A boolean; user input accepts y/n, yes/no, true/false, on/off, and 1/0.
+    Boolean,
+
+    /// A signed integer
+    Integer,
+
+    /// A floating point number
+    Float,
+}
+
+impl VariableType {
+    /// Returns the type of variable that a default value declares.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => VariableType::Boolean,
+            Value::Number(number) if number.is_f64() => VariableType::Float,
+            Value::Number(_) => VariableType::Integer,
+            _ => VariableType::String,
+        }
+    }
+
+    /// Parses user input into a value of this type.
+    pub fn parse_input(&self, input: &str) -> Result<Value, VariableValueError> {
+        match self {
+            VariableType::String => Ok(Value::String(input.to_string())),
+            VariableType::Boolean => {
+                match input.to_lowercase().as_str() {
+                    "y" | "yes" | "true" | "t" | "on" | "1" => Ok(Value::Bool(true)),
+                    "n" | "no" | "false" | "f" | "off" | "0" => Ok(Value::Bool(false)),
+                    _ => Err(VariableValueError::InvalidBoolean(input.to_string())),
+                }
+            },
+            VariableType::Integer => {
+                match input.parse::<i64>() {
+                    Ok(int_value) => Ok(Value::from(int_value)),
+                    Err(_) => Err(VariableValueError::InvalidInteger(input.to_string())),
+                }
+            },
+            VariableType::Float => {
+                match input.parse::<f64>() {
+                    Ok(fp_value) if fp_value.is_finite() => Ok(Value::from(fp_value)),
+                    _ => Err(VariableValueError::InvalidFloat(input.to_string())),
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for VariableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableType::String => write!(f, "string"),
+            VariableType::Boolean => write!(f, "boolean"),
+            VariableType::Integer => write!(f, "integer"),
+            VariableType::Float => write!(f, "float"),
+        }
+    }
+}
+
+/// Built-in kinds of string values that a variable can be required to match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    /// A programming language identifier: letters, digits, and underscores, not starting with a digit
+    Identifier,
+
+    /// A semantic version, such as `1.2.3` or `0.1.0-alpha.1`
+    Semver,
+
+    /// An email address
+    Email,
+
+    /// A URL with a scheme and host, such as `https://example.com/path`
+    Url,
+}
+
+impl ValueKind {
+    /// Parses the name of a kind, as used in configuration files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "identifier" => Some(ValueKind::Identifier),
+            "semver" => Some(ValueKind::Semver),
+            "email" => Some(ValueKind::Email),
+            "url" => Some(ValueKind::Url),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the value is of this kind.
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueKind::Identifier => IDENTIFIER_REGEX.is_match(value),
+            ValueKind::Semver => SEMVER_REGEX.is_match(value),
+            ValueKind::Email => EMAIL_REGEX.is_match(value),
+            ValueKind::Url => URL_REGEX.is_match(value),
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueKind::Identifier => write!(f, "identifier"),
+            ValueKind::Semver => write!(f, "semver"),
+            ValueKind::Email => write!(f, "email"),
+            ValueKind::Url => write!(f, "url"),
+        }
+    }
+}
+
+/// Rules that a variable's value must satisfy. All the rules are optional.
+#[derive(Debug, Clone, Default)]
+pub struct VariableValidation {
+    /// The value must not be null, an empty string, or an empty list.
+    pub required: bool,
+
+    /// A regex that string values must match.
+    pub pattern: Option<Regex>,
+
+    /// The minimum length of a string, in characters, or the minimum number of selected choices.
+    pub min_length: Option<usize>,
+
+    /// The maximum length of a string, in characters, or the maximum number of selected choices.
+    pub max_length: Option<usize>,
+
+    /// The minimum of a numeric value, inclusive.
+    pub min: Option<f64>,
+
+    /// The maximum of a numeric value, inclusive.
+    pub max: Option<f64>,
+
+    /// A built-in kind that string values must be.
+    pub kind: Option<ValueKind>,
+}
+
+impl VariableValidation {
+    /// Checks the value against every rule, returning the first one it breaks.
+    pub fn validate(&self, value: &Value) -> Result<(), VariableValueError> {
+        let is_empty = match value {
+            Value::Null => true,
+            Value::String(string_value) => string_value.is_empty(),
+            Value::Array(values) => values.is_empty(),
+            _ => false,
+        };
+        if is_empty {
+            // Empty values are only checked for presence; length and format rules don't apply
+            return match self.required {
+                true => Err(VariableValueError::Required),
+                false => Ok(()),
+            };
+        }
+
+        let length = match value {
+            Value::String(string_value) => Some(string_value.chars().count()),
+            Value::Array(values) => Some(values.len()),
+            _ => None,
+        };
+        if let Some(length) = length {
+            if let Some(min_length) = self.min_length && length < min_length {
+                return Err(VariableValueError::TooShort(display_value(value), min_length));
+            }
+            if let Some(max_length) = self.max_length && length > max_length {
+                return Err(VariableValueError::TooLong(display_value(value), max_length));
+            }
+        }
+
+        if let Some(number) = value.as_f64() {
+            if let Some(min) = self.min && number < min {
+                return Err(VariableValueError::BelowMinimum(display_value(value), min));
+            }
+            if let Some(max) = self.max && number > max {
+                return Err(VariableValueError::AboveMaximum(display_value(value), max));
+            }
+        }
+
+        if let Value::String(string_value) = value {
+            if let Some(pattern) = &self.pattern && !pattern.is_match(string_value) {
+                return Err(VariableValueError::PatternMismatch(string_value.clone(), pattern.as_str().to_string()));
+            }
+            if let Some(kind) = self.kind && !kind.matches(string_value) {
+                return Err(VariableValueError::NotOfKind(string_value.clone(), kind));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for VariableValidation {
+    fn eq(&self, other: &Self) -> bool {
+        self.required == other.required
+            && self.pattern.as_ref().map(Regex::as_str) == other.pattern.as_ref().map(Regex::as_str)
+            && self.min_length == other.min_length
+            && self.max_length == other.max_length
+            && self.min == other.min
+            && self.max == other.max
+            && self.kind == other.kind
+    }
+}
+
+/// The definition of a single template variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateVariable {
+    /// The name the variable is referenced by in templates.
+    name: String,
+
+    /// The value used if the invoker doesn't provide one. Its type is the type of the variable.
+    default: Value,
+
+    /// The human-friendly question to ask when prompting for a value, if any.
+    prompt: Option<String>,
+
+    /// Longer help text explaining the variable, if any.
+    help: Option<String>,
+
+    /// The fixed set of values this variable can take, if it is restricted to one.
+    choices: Option<Vec<Value>>,
+
+    /// Whether multiple choices can be selected, in which case the value is a list.
+    multiple: bool,
+
+    /// The rules the variable's value must satisfy.
+    validation: VariableValidation,
+
+    /// A condition on earlier values that decides whether this variable is relevant at all; see
+    /// [crate::template_rendering::evaluate_condition] for the syntax. When the condition is false,
+    /// the user is not prompted, and the variable takes its default value.
+    condition: Option<String>,
+
+    /// The name of the section this variable is grouped under when prompting, if any.
+    group: Option<String>,
+
+    /// Whether this is an advanced variable, which is only prompted for on request and otherwise
+    /// takes its default value.
+    advanced: bool,
+}
+
+impl TemplateVariable {
+    /// Creates a new variable with the given default, and no prompt or help text.
+    pub fn new(name: String, default: Value) -> Self {
+        Self {
+            name,
+            default,
+            prompt: None,
+            help: None,
+            choices: None,
+            multiple: false,
+            validation: VariableValidation::default(),
+            condition: None,
+            group: None,
+            advanced: false,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_default(&self) -> &Value {
+        &self.default
+    }
+
+    /// Returns the type of the variable, as declared by its default value.
+    pub fn get_type(&self) -> VariableType {
+        VariableType::of(&self.default)
+    }
+
+    pub fn set_prompt(&mut self, prompt: Option<String>) {
+        self.prompt = prompt;
+    }
+
+    /// Returns the text to prompt the user with; this falls back to the variable name if no prompt
+    /// was configured.
+    pub fn get_prompt(&self) -> &str {
+        self.prompt.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn set_help(&mut self, help: Option<String>) {
+        self.help = help;
+    }
+
+    pub fn get_help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    /// Restricts the variable to a fixed set of values, or removes the restriction if None.
+    pub fn set_choices(&mut self, choices: Option<Vec<Value>>) {
+        self.choices = choices;
+    }
+
+    pub fn get_choices(&self) -> Option<&[Value]> {
+        self.choices.as_deref()
+    }
+
+    /// Sets whether multiple choices can be selected. This only has an effect on variables with
+    /// choices.
+    pub fn set_multiple(&mut self, multiple: bool) {
+        self.multiple = multiple;
+    }
+
+    pub fn is_multiple(&self) -> bool {
+        self.multiple && self.choices.is_some()
+    }
+
+    pub fn set_validation(&mut self, validation: VariableValidation) {
+        self.validation = validation;
+    }
+
+    pub fn get_validation(&self) -> &VariableValidation {
+        &self.validation
+    }
+
+    /// Returns whether the default value is computed, i.e. it is a Tera template referencing other
+    /// variables, such as `{{ project_name | slugify }}`.
+    pub fn has_computed_default(&self) -> bool {
+        match &self.default {
+            Value::String(default) => is_template_string(default),
+            _ => false,
+        }
+    }
+
+    pub fn set_condition(&mut self, condition: Option<String>) {
+        self.condition = condition;
+    }
+
+    pub fn get_condition(&self) -> Option<&str> {
+        self.condition.as_deref()
+    }
+
+    pub fn set_group(&mut self, group: Option<String>) {
+        self.group = group;
+    }
+
+    pub fn get_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    pub fn set_advanced(&mut self, advanced: bool) {
+        self.advanced = advanced;
+    }
+
+    pub fn is_advanced(&self) -> bool {
+        self.advanced
+    }
+
+    /// Returns whether the variable is relevant given the values in `context`, i.e. whether its
+    /// condition (if it has one) is true.
+    pub fn is_enabled(&self, context: &tera::Context) -> tera::Result<bool> {
+        match &self.condition {
+            Some(condition) => evaluate_condition(condition, context),
+            None => Ok(true),
+        }
+    }
+
+    /// Returns the names that the variable's definition references, such as the variables used in a
+    /// computed default or its condition. This can include names that aren't variables (e.g. filters
+    /// or loop variables), so callers should only consider the names they know to be variables.
+    pub fn get_referenced_names(&self) -> Vec<String> {
+        let mut names = match &self.default {
+            Value::String(default) if self.has_computed_default() => referenced_names(default),
+            _ => Vec::new(),
+        };
+
+        if let Some(condition) = &self.condition {
+            let condition_names = match is_template_string(condition) {
+                true => referenced_names(condition),
+                false => referenced_names(&format!("{{{{ {} }}}}", condition)),
+            };
+            for name in condition_names {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Returns the default value, with computed defaults rendered against `context`.
+    pub fn resolve_default(&self, context: &tera::Context) -> tera::Result<Value> {
+        match &self.default {
+            Value::String(default) if self.has_computed_default() => {
+                render_one_off(default, context, false).map(Value::String)
+            },
+            default => Ok(default.clone()),
+        }
+    }
+
+    /// Parses user input into a value for this variable, and validates it.
+    ///
+    /// For variables with choices, a choice can be given either as its 1-based position in the list
+    /// or as the choice itself, and multiple choices are separated by commas. Otherwise, the input is
+    /// parsed according to the variable's type.
+    pub fn parse_input(&self, input: &str) -> Result<Value, VariableValueError> {
+        let value = self.parse_input_value(input)?;
+        self.validation.validate(&value)?;
+        Ok(value)
+    }
+
+    fn parse_input_value(&self, input: &str) -> Result<Value, VariableValueError> {
+        match &self.choices {
+            None => self.get_type().parse_input(input),
+            Some(choices) => {
+                if self.multiple {
+                    let mut selected = Vec::new();
+                    for choice_input in input.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+                        let choice = parse_choice(choices, choice_input)?;
+                        if !selected.contains(&choice) {
+                            selected.push(choice);
+                        }
+                    }
+                    Ok(Value::Array(selected))
+                } else {
+                    parse_choice(choices, input.trim())
+                }
+            },
+        }
+    }
+
+    /// Checks that a value supplied for this variable, without going through [Self::parse_input], is
+    /// acceptable: it must be one of the choices (if there are any), and satisfy the validation rules.
+    pub fn validate_value(&self, value: &Value) -> Result<(), VariableValueError> {
+        self.validate_choices(value)?;
+        self.validation.validate(value)
+    }
+
+    /// Checks that a value is one of the variable's choices, if it has any.
+    pub fn validate_choices(&self, value: &Value) -> Result<(), VariableValueError> {
+        if let Some(choices) = &self.choices {
+            if self.multiple {
+                match value {
+                    Value::Array(selected) => {
+                        if let Some(invalid_choice) = selected.iter().find(|v| !choices.contains(v)) {
+                            return Err(VariableValueError::NotAChoice(display_value(invalid_choice)));
+                        }
+                    },
+                    _ => return Err(VariableValueError::NotAList(display_value(value))),
+                };
+            } else if !choices.contains(value) {
+                return Err(VariableValueError::NotAChoice(display_value(value)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether a string contains Tera tags, and so needs to be rendered.
+pub fn is_template_string(value: &str) -> bool {
+    value.contains("{{") || value.contains("{%")
+}
+
+/// Finds every identifier used inside the Tera tags of a template string that could be a variable,
+/// in order of first use. Identifiers inside string literals and attribute accesses (`a.b`, where
+/// `b` is skipped) are not included, and neither are keywords, the names of filters (`| upper`),
+/// tests (`is defined`), and functions, or the names of arguments (`default(value=...)`).
+fn referenced_names(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for tag in TEMPLATE_TAG_REGEX.captures_iter(template) {
+        let tag_contents = tag.get(1).or_else(|| tag.get(2)).map(|m| m.as_str()).unwrap_or_default();
+        let tag_contents = STRING_LITERAL_REGEX.replace_all(tag_contents, " ");
+        let mut after_is = false;
+        for identifier in TEMPLATE_IDENTIFIER_REGEX.captures_iter(&tag_contents) {
+            let name = &identifier[2];
+            let is_filter = identifier.get(1).is_some();
+            let is_test = after_is && name != "not";
+            after_is = name == "is" || (after_is && name == "not");
+
+            let following = tag_contents[identifier.get(2).map_or(0, |m| m.end())..].trim_start();
+            let is_call_or_argument = following.starts_with('(') || (following.starts_with('=') && !following.starts_with("=="));
+            if is_filter || is_test || is_call_or_argument || TERA_KEYWORDS.contains(&name) {
+                continue;
+            }
+
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names
+}
+
+/// Finds the choice matching the input, either by its text or by its 1-based position.
+fn parse_choice(choices: &[Value], input: &str) -> Result<Value, VariableValueError> {
+    if let Some(choice) = choices.iter().find(|c| display_value(c) == input) {
+        return Ok(choice.clone());
+    }
+
+    match input.parse::<usize>() {
+        Ok(position) if position >= 1 && position <= choices.len() => Ok(choices[position - 1].clone()),
+        _ => Err(VariableValueError::NotAChoice(input.to_string())),
+    }
+}
+
+/// Errors for values that can't be used for a particular variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableValueError {
+    InvalidBoolean(String),
+    InvalidInteger(String),
+    InvalidFloat(String),
+    NotAChoice(String),
+    NotAList(String),
+    Required,
+    TooShort(String, usize),
+    TooLong(String, usize),
+    BelowMinimum(String, f64),
+    AboveMaximum(String, f64),
+    PatternMismatch(String, String),
+    NotOfKind(String, ValueKind),
+}
+
+impl fmt::Display for VariableValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableValueError::InvalidBoolean(input) => write!(f, "'{}' is not a yes/no value", input),
+            VariableValueError::InvalidInteger(input) => write!(f, "'{}' is not an integer", input),
+            VariableValueError::InvalidFloat(input) => write!(f, "'{}' is not a number", input),
+            VariableValueError::NotAChoice(input) => write!(f, "'{}' is not one of the choices", input),
+            VariableValueError::NotAList(input) => write!(f, "'{}' is not a list of choices", input),
+            VariableValueError::Required => write!(f, "a value is required"),
+            VariableValueError::TooShort(input, min_length) => write!(f, "'{}' is shorter than the minimum length of {}", input, min_length),
+            VariableValueError::TooLong(input, max_length) => write!(f, "'{}' is longer than the maximum length of {}", input, max_length),
+            VariableValueError::BelowMinimum(input, min) => write!(f, "{} is less than the minimum of {}", input, min),
+            VariableValueError::AboveMaximum(input, max) => write!(f, "{} is greater than the maximum of {}", input, max),
+            VariableValueError::PatternMismatch(input, pattern) => write!(f, "'{}' does not match the pattern '{}'", input, pattern),
+            VariableValueError::NotOfKind(input, kind) => write!(f, "'{}' is not a valid {}", input, kind),
+        }
+    }
+}
+
+/// Formats a variable value for display to the user, e.g. as the default in a prompt. Strings are
+/// shown without quotes and null is shown as an empty string.
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string_value) => string_value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn variable_type_of_works() {
+        assert_eq!(VariableType::of(&Value::from("abcd")), VariableType::String);
+        assert_eq!(VariableType::of(&Value::Null), VariableType::String);
+        assert_eq!(VariableType::of(&Value::from(true)), VariableType::Boolean);
+        assert_eq!(VariableType::of(&Value::from(12345)), VariableType::Integer);
+        assert_eq!(VariableType::of(&Value::from(1.5)), VariableType::Float);
+    }
+
+    #[test]
+    pub fn parse_input_works() {
+        assert_eq!(VariableType::String.parse_input("12"), Ok(Value::from("12")));
+
+        assert_eq!(VariableType::Boolean.parse_input("y"), Ok(Value::from(true)));
+        assert_eq!(VariableType::Boolean.parse_input("No"), Ok(Value::from(false)));
+        assert!(VariableType::Boolean.parse_input("maybe").is_err());
+
+        assert_eq!(VariableType::Integer.parse_input("-42"), Ok(Value::from(-42)));
+        assert!(VariableType::Integer.parse_input("4.2").is_err());
+
+        assert_eq!(VariableType::Float.parse_input("4.5"), Ok(Value::from(4.5)));
+        assert!(VariableType::Float.parse_input("NaN").is_err());
+    }
+
+    #[test]
+    pub fn choice_variables_only_accept_choices() {
+        let mut variable = TemplateVariable::new("license".to_string(), Value::from("MIT"));
+        variable.set_choices(Some(vec![Value::from("MIT"), Value::from("Apache-2.0"), Value::from("GPL-3.0")]));
+
+        assert_eq!(variable.parse_input("Apache-2.0"), Ok(Value::from("Apache-2.0")));
+        assert_eq!(variable.parse_input("3"), Ok(Value::from("GPL-3.0")));
+        assert!(variable.parse_input("4").is_err());
+        assert!(variable.parse_input("BSD").is_err());
+
+        assert!(variable.validate_value(&Value::from("MIT")).is_ok());
+        assert_eq!(variable.validate_value(&Value::from("BSD")), Err(VariableValueError::NotAChoice("BSD".to_string())));
+    }
+
+    #[test]
+    pub fn multiple_choice_variables_produce_lists() {
+        let mut variable = TemplateVariable::new("ci".to_string(), Value::Array(vec![]));
+        variable.set_choices(Some(vec![Value::from("github"), Value::from("gitlab"), Value::from("jenkins")]));
+        variable.set_multiple(true);
+
+        assert_eq!(variable.parse_input("1, jenkins,1"), Ok(Value::from(vec!["github", "jenkins"])));
+        assert!(variable.parse_input("github,travis").is_err());
+
+        assert!(variable.validate_value(&Value::from(vec!["gitlab"])).is_ok());
+        assert!(variable.validate_value(&Value::from(vec!["travis"])).is_err());
+        assert!(variable.validate_value(&Value::from("gitlab")).is_err());
+    }
+
+    #[test]
+    pub fn validation_rules_are_enforced() {
+        let mut variable = TemplateVariable::new("crate_name".to_string(), Value::Null);
+        variable.set_validation(VariableValidation {
+            required: true,
+            pattern: Some(Regex::new("^[a-z_]+$").unwrap()),
+            max_length: Some(8),
+            kind: Some(ValueKind::Identifier),
+            ..Default::default()
+        });
+
+        assert_eq!(variable.validate_value(&Value::Null), Err(VariableValueError::Required));
+        assert_eq!(variable.parse_input("my_crate"), Ok(Value::from("my_crate")));
+        assert!(matches!(variable.parse_input("my crate"), Err(VariableValueError::PatternMismatch(_, _))));
+        assert!(matches!(variable.parse_input("my_long_crate"), Err(VariableValueError::TooLong(_, 8))));
+
+        let mut port = TemplateVariable::new("port".to_string(), Value::from(8080));
+        port.set_validation(VariableValidation {
+            min: Some(1.0),
+            max: Some(65535.0),
+            ..Default::default()
+        });
+
+        assert!(port.parse_input("443").is_ok());
+        assert!(matches!(port.parse_input("0"), Err(VariableValueError::BelowMinimum(_, _))));
+        assert!(matches!(port.validate_value(&Value::from(70000)), Err(VariableValueError::AboveMaximum(_, _))));
+    }
+
+    #[test]
+    pub fn computed_defaults_work() {
+        let variable = TemplateVariable::new("crate_name".to_string(), Value::from("{{ project_name | slugify }}-{{ suffix }}"));
+        assert!(variable.has_computed_default());
+        assert_eq!(variable.get_referenced_names(), vec!["project_name", "suffix"]);
+
+        let mut context = tera::Context::new();
+        context.insert("project_name", "My Project");
+        context.insert("suffix", "rs");
+        assert_eq!(variable.resolve_default(&context).unwrap(), Value::from("my-project-rs"));
+
+        let literal = TemplateVariable::new("literal".to_string(), Value::from("plain"));
+        assert!(!literal.has_computed_default());
+        assert!(literal.get_referenced_names().is_empty());
+        assert_eq!(literal.resolve_default(&tera::Context::new()).unwrap(), Value::from("plain"));
+    }
+
+    #[test]
+    pub fn conditions_work() {
+        let mut variable = TemplateVariable::new("ci_provider".to_string(), Value::from("github"));
+        variable.set_condition(Some("use_ci and not use_docker".to_string()));
+        assert_eq!(variable.get_referenced_names(), vec!["use_ci", "use_docker"]);
+
+        let mut context = tera::Context::new();
+        context.insert("use_ci", &true);
+        context.insert("use_docker", &false);
+        assert!(variable.is_enabled(&context).unwrap());
+
+        context.insert("use_ci", &false);
+        assert!(!variable.is_enabled(&context).unwrap());
+    }
+
+    #[test]
+    pub fn referenced_names_skips_literals_and_attributes() {
+        assert_eq!(referenced_names(r#"{{ author.name ~ "other" }}{% if use_ci %}{{ 'skipped' }}{% endif %}"#), vec!["author", "use_ci"]);
+    }
+
+    #[test]
+    pub fn referenced_names_skips_filters_tests_and_keywords() {
+        assert_eq!(referenced_names("{{ name | upper }}-{{ name|snake_case|replace(from=sep, to='_') }}"), vec!["name", "sep"]);
+        assert_eq!(referenced_names("{% if license is defined and not (lower is not string) %}{{ range(end=count) }}{% endif %}"), vec!["license", "lower", "count"]);
+        assert_eq!(referenced_names("{% for item in items %}{{ loop.index }}{{ item | default(value=upper) }}{% endfor %}"), vec!["item", "items", "upper"]);
+    }
+
+    #[test]
+    pub fn value_kinds_work() {
+        assert!(ValueKind::Identifier.matches("_my_ident1"));
+        assert!(!ValueKind::Identifier.matches("1ident"));
+        assert!(ValueKind::Semver.matches("1.2.3-alpha.1+build.5"));
+        assert!(!ValueKind::Semver.matches("1.2"));
+        assert!(ValueKind::Email.matches("someone@example.com"));
+        assert!(!ValueKind::Email.matches("someone@localhost"));
+        assert!(ValueKind::Url.matches("https://example.com/path?query=1"));
+        assert!(!ValueKind::Url.matches("example.com"));
+    }
+}
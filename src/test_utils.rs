@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+
+// Helpers shared by the tests of several modules
+
+/// A scratch directory under the system's temporary directory, for tests that need files on disk.
+/// It's removed when dropped, so it's cleaned up even if an assertion fails.
+pub struct TestDirectory {
+    path: PathBuf,
+}
+
+impl TestDirectory {
+    /// Creates an empty directory for the test called `name`. The process id is part of the path,
+    /// so separate test runs don't trip over each other.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("utsusu-{}-test-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TestDirectory { path }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path)
+    }
+
+    /// Writes `contents` to the file at `path` inside the directory, creating its parent directories
+    /// as needed, and returns the full path of the file.
+    pub fn write<C: AsRef<[u8]>>(&self, path: &str, contents: C) -> PathBuf {
+        let path = self.path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
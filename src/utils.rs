@@ -1,6 +1,7 @@
 use std::io;
 use std::io::Write;
 use crate::template_config::TemplateConfig;
+use crate::template_variable::{display_value, TemplateVariable, VariableType};
 
 /// Prompts the user for input, then returns their input, with trailing whitespace (including
 /// newlines) removed.
@@ -14,25 +15,131 @@ pub fn get_user_input(prompt: &str) -> Option<String> {
     let _ = io::stdout().flush();  // Ensure the message is displayed to the user before requesting input
     let _ = io::stdin().read_line(&mut input);
     let trimmed_input = input.trim();
-    if trimmed_input == "" {
+    if trimmed_input.is_empty() {
         None
     } else {
         Some(trimmed_input.to_string())
     }
 }
 
+/// Prompts the user for a value for a single variable, showing its help text (if any) first.
+///
+/// Variables with choices are shown as a numbered menu. Input is parsed into the declared type of
+/// the variable (or must be one of its choices) and checked against its validation rules; if it
+/// isn't valid, the user is told why and prompted again.
+///
+/// `default` is the value shown as the default (and validated if it's accepted); for computed
+/// defaults, this should already be rendered.
+///
+/// Returns None if the user accepted the default.
+pub fn get_user_variable_choice(variable: &TemplateVariable, default: &tera::Value) -> Option<tera::Value> {
+    if let Some(help) = variable.get_help() {
+        println!("{}", help);
+    }
+
+    let prompt = match variable.get_choices() {
+        Some(choices) => {
+            // Show a numbered menu of the choices, with the default choice(s) given by number
+            println!("{}:", variable.get_prompt());
+            for (i, choice) in choices.iter().enumerate() {
+                println!("  {}) {}", i + 1, display_value(choice));
+            }
+
+            let default_values = match default {
+                tera::Value::Array(default_values) => default_values.clone(),
+                default_value => vec![default_value.clone()],
+            };
+            let default_positions: Vec<String> = default_values.iter()
+                .filter_map(|d| choices.iter().position(|c| c == d))
+                .map(|i| (i + 1).to_string())
+                .collect();
+
+            if variable.is_multiple() {
+                format!("Choose any, separated by commas [{}]: ", default_positions.join(","))
+            } else {
+                format!("Choose one [{}]: ", default_positions.join(","))
+            }
+        },
+        None => {
+            match variable.get_type() {
+                VariableType::Boolean => format!("{} (y/n) [{}]: ", variable.get_prompt(), display_value(default)),
+                _ => format!("{} [{}]: ", variable.get_prompt(), display_value(default)),
+            }
+        },
+    };
+
+    loop {
+        match get_user_input(&prompt) {
+            Some(trimmed_input) => {
+                match variable.parse_input(&trimmed_input) {
+                    Ok(value) => return Some(value),
+                    Err(value_error) => println!("Invalid value: {}", value_error),
+                };
+            },
+            None => {
+                // The default has to pass validation too, e.g. a required variable with no default
+                match variable.validate_value(default) {
+                    Ok(()) => return None,
+                    Err(value_error) => println!("Invalid value: {}", value_error),
+                };
+            },
+        };
+    }
+}
+
 /// Iterates through the variables defined in the template and prompts the user for values for each
-/// of them.
+/// of them, skipping variables whose condition is false. Advanced variables are skipped too, unless
+/// `include_advanced` is set. A heading is shown at the start of each group of variables.
 ///
 /// Returns a Tera Context with the values that were explicitly overridden by the user. Values left
 /// as the default are not included in the context.
-pub fn get_user_variable_choices(config: &TemplateConfig) -> tera::Context {
+pub fn get_user_variable_choices(config: &TemplateConfig, include_advanced: bool) -> tera::Context {
     let mut user_variables_context: tera::Context = tera::Context::new();
 
-    for (var_name, default_var_value) in config.get_variable_items() {
-        let prompt = format!("{} [{}]: ", var_name, default_var_value);
-        if let Some(trimmed_input) = get_user_input(&prompt) {
-            user_variables_context.insert(var_name, &trimmed_input);
+    // Every answer (or accepted default) so far, used to render computed defaults. Variables are
+    // prompted for in dependency order, so everything a computed default references is in here.
+    let mut answers_context: tera::Context = tera::Context::new();
+
+    let mut current_group: Option<&str> = None;
+
+    for variable in config.get_variables() {
+        let enabled = match variable.is_enabled(&answers_context) {
+            Ok(enabled) => enabled,
+            Err(tera_error) => {
+                println!("Error evaluating the condition for '{}': {}", variable.get_name(), tera_error);
+                true
+            },
+        };
+
+        let default = match variable.resolve_default(&answers_context) {
+            Ok(default) => default,
+            Err(tera_error) => {
+                println!("Error rendering the default value for '{}': {}", variable.get_name(), tera_error);
+                variable.get_default().clone()
+            },
+        };
+
+        // Variables that aren't relevant to the answers so far (or are advanced, when those aren't
+        // wanted) aren't asked about, and keep their default
+        if !enabled || (variable.is_advanced() && !include_advanced) {
+            answers_context.insert(variable.get_name(), &default);
+            continue;
+        }
+
+        if variable.get_group() != current_group {
+            current_group = variable.get_group();
+            println!();
+            if let Some(group) = current_group {
+                println!("== {} ==", group);
+            }
+        }
+
+        match get_user_variable_choice(variable, &default) {
+            Some(value) => {
+                user_variables_context.insert(variable.get_name(), &value);
+                answers_context.insert(variable.get_name(), &value);
+            },
+            None => answers_context.insert(variable.get_name(), &default),
         };
     }
 
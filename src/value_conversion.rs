@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+use saphyr::{Scalar, ScalarOwned, Yaml, YamlOwned};
+use tera::{Map, Value};
+
+// Convert between Tera's values (which are JSON values) and YAML and TOML, for reading configurations
+// in any format and for merging rendered documents
+
+/// The key serde uses to carry TOML datetimes through other formats
+const TOML_DATETIME_KEY: &str = "$__toml_private_datetime";
+
+/// Converts a YAML scalar into the equivalent Tera value, keeping its type.
+pub(crate) fn scalar_to_value(scalar: &ScalarOwned) -> Value {
+    match scalar {
+        ScalarOwned::Null => Value::Null,
+        ScalarOwned::Boolean(bool_value) => Value::from(*bool_value),
+        ScalarOwned::Integer(int_value) => Value::from(*int_value),
+        ScalarOwned::FloatingPoint(fp_value) => Value::from(fp_value.into_inner()),
+        ScalarOwned::String(string_value) => Value::from(string_value.as_str()),
+    }
+}
+
+/// Converts a YAML document into the equivalent Tera value. Mapping keys that aren't strings are
+/// written out as strings, and aliases become null.
+pub(crate) fn yaml_to_value(yaml: &YamlOwned) -> Value {
+    match yaml {
+        YamlOwned::Value(scalar) => scalar_to_value(scalar),
+        YamlOwned::Representation(raw_value, _, _) => Value::String(raw_value.clone()),
+        YamlOwned::Sequence(seq) => Value::Array(seq.iter().map(yaml_to_value).collect()),
+        YamlOwned::Mapping(mapping) => Value::Object(mapping.iter()
+            .map(|(key, value)| {
+                let key = match yaml_to_value(key) {
+                    Value::String(key) => key,
+                    other => other.to_string(),
+                };
+                (key, yaml_to_value(value))
+            })
+            .collect()),
+        YamlOwned::Tagged(_, tagged_value) => yaml_to_value(tagged_value),
+        YamlOwned::Alias(_) | YamlOwned::BadValue => Value::Null,
+    }
+}
+
+/// Converts a Tera value into the equivalent YAML, for writing out.
+pub(crate) fn value_to_yaml(value: &Value) -> Yaml<'static> {
+    match value {
+        Value::Null => Yaml::Value(Scalar::Null),
+        Value::Bool(bool_value) => Yaml::Value(Scalar::Boolean(*bool_value)),
+        Value::Number(number) => Yaml::Value(Scalar::parse_from_cow(Cow::Owned(number.to_string()))),
+        Value::String(string_value) => Yaml::Value(Scalar::String(Cow::Owned(string_value.clone()))),
+        Value::Array(items) => Yaml::Sequence(items.iter().map(value_to_yaml).collect()),
+        Value::Object(map) => Yaml::Mapping(map.iter()
+            .map(|(key, value)| (Yaml::Value(Scalar::String(Cow::Owned(key.clone()))), value_to_yaml(value)))
+            .collect()),
+    }
+}
+
+/// Converts a Tera value into the equivalent YAML, for parsing as a configuration.
+pub(crate) fn value_to_owned_yaml(value: &Value) -> YamlOwned {
+    match value {
+        Value::Null => YamlOwned::Value(ScalarOwned::Null),
+        Value::Bool(bool_value) => YamlOwned::Value(ScalarOwned::Boolean(*bool_value)),
+        Value::Number(number) => match number.as_i64() {
+            Some(int_value) => YamlOwned::Value(ScalarOwned::Integer(int_value)),
+            None => YamlOwned::Value(ScalarOwned::FloatingPoint(number.as_f64().unwrap_or(f64::NAN).into())),
+        },
+        Value::String(string_value) => YamlOwned::Value(ScalarOwned::String(string_value.clone())),
+        Value::Array(items) => YamlOwned::Sequence(items.iter().map(value_to_owned_yaml).collect()),
+        Value::Object(map) => YamlOwned::Mapping(map.iter()
+            .map(|(key, value)| (YamlOwned::Value(ScalarOwned::String(key.clone())), value_to_owned_yaml(value)))
+            .collect()),
+    }
+}
+
+/// Converts a TOML value to the equivalent YAML, with datetimes as strings.
+pub(crate) fn toml_to_yaml(value: &toml::Value) -> YamlOwned {
+    match value {
+        toml::Value::String(string_value) => YamlOwned::Value(ScalarOwned::String(string_value.clone())),
+        toml::Value::Integer(int_value) => YamlOwned::Value(ScalarOwned::Integer(*int_value)),
+        toml::Value::Float(fp_value) => YamlOwned::Value(ScalarOwned::FloatingPoint((*fp_value).into())),
+        toml::Value::Boolean(bool_value) => YamlOwned::Value(ScalarOwned::Boolean(*bool_value)),
+        toml::Value::Datetime(datetime) => YamlOwned::Value(ScalarOwned::String(datetime.to_string())),
+        toml::Value::Array(items) => YamlOwned::Sequence(items.iter().map(toml_to_yaml).collect()),
+        toml::Value::Table(table) => YamlOwned::Mapping(table.iter()
+            .map(|(key, value)| (YamlOwned::Value(ScalarOwned::String(key.clone())), toml_to_yaml(value)))
+            .collect()),
+    }
+}
+
+/// Converts a value to TOML, leaving out nulls, which TOML can't represent.
+pub(crate) fn value_to_toml(value: &Value) -> Option<toml::Value> {
+    match value {
+        Value::Null => None,
+        Value::Bool(bool_value) => Some(toml::Value::Boolean(*bool_value)),
+        Value::Number(number) => match number.as_i64() {
+            Some(int_value) => Some(toml::Value::Integer(int_value)),
+            None => number.as_f64().map(toml::Value::Float),
+        },
+        Value::String(string_value) => Some(toml::Value::String(string_value.clone())),
+        Value::Array(items) => Some(toml::Value::Array(items.iter().filter_map(value_to_toml).collect())),
+        Value::Object(map) => match get_toml_datetime(map) {
+            Some(datetime) => Some(toml::Value::Datetime(datetime)),
+            None => Some(toml::Value::Table(map.iter()
+                .filter_map(|(key, value)| value_to_toml(value).map(|value| (key.clone(), value)))
+                .collect())),
+        },
+    }
+}
+
+/// Returns the datetime an object stands in for, if it's a TOML datetime that was parsed into a value.
+fn get_toml_datetime(map: &Map<String, Value>) -> Option<toml::value::Datetime> {
+    match map.get(TOML_DATETIME_KEY) {
+        Some(Value::String(datetime)) if map.len() == 1 => datetime.parse().ok(),
+        _ => None,
+    }
+}